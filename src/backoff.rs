@@ -0,0 +1,142 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Recovery strategy that lengthens the open period each time a half-open
+/// probe fails and the breaker reopens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// Factor the open duration is multiplied by on every reopen.
+    pub multiplier: f64,
+    /// Upper bound on the open duration.
+    pub max_timeout: Duration,
+    /// Time the breaker must stay closed before the open duration goes back
+    /// to the configured `timeout`.
+    pub reset_after: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            multiplier: 2.0,
+            max_timeout: Duration::from_secs(600),
+            reset_after: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    // Open duration after `reopens` consecutive failed recoveries.
+    pub(crate) fn open_duration(&self, base: Duration, reopens: u32) -> Duration {
+        let exponent = i32::try_from(reopens).unwrap_or(i32::MAX);
+        let secs = base.as_secs_f64() * self.multiplier.powi(exponent);
+        if secs.is_finite() && secs < self.max_timeout.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max_timeout
+        }
+    }
+}
+
+/// Randomization applied to every open duration so breakers that tripped
+/// together don't all probe the dependency at the same moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Jitter {
+    /// Use the open duration as computed.
+    #[default]
+    None,
+    /// Anywhere between zero and the open duration.
+    Full,
+    /// Half the open duration plus a random share of the other half.
+    Equal,
+    /// Anywhere between `timeout` and three times the previous open
+    /// duration, capped at the open duration computed by `Backoff` (or three
+    /// times `timeout` without backoff). The backoff multiplier and
+    /// `reset_after` still bound how far it can grow.
+    Decorrelated,
+}
+
+impl Jitter {
+    pub(crate) fn apply<R: Rng>(
+        self,
+        rng: &mut R,
+        duration: Duration,
+        base: Duration,
+        previous: Duration,
+        cap: Duration,
+    ) -> Duration {
+        match self {
+            Jitter::None => duration,
+            Jitter::Full => duration.mul_f64(rng.gen_range(0.0..=1.0)),
+            Jitter::Equal => duration / 2 + (duration / 2).mul_f64(rng.gen_range(0.0..=1.0)),
+            Jitter::Decorrelated => {
+                let upper = previous.saturating_mul(3).min(cap).max(base);
+                base + (upper - base).mul_f64(rng.gen_range(0.0..=1.0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn open_duration_grows_up_to_max_timeout() {
+        let backoff = Backoff {
+            multiplier: 3.0,
+            max_timeout: Duration::from_secs(60),
+            reset_after: Duration::from_secs(60),
+        };
+        let base = Duration::from_secs(5);
+        let durations: Vec<_> = (0..4)
+            .map(|reopens| backoff.open_duration(base, reopens))
+            .collect();
+        assert_eq!(durations, [5, 15, 45, 60].map(Duration::from_secs).to_vec());
+        assert_eq!(backoff.open_duration(base, u32::MAX), backoff.max_timeout);
+    }
+
+    #[test]
+    fn jitter_stays_in_range_and_follows_seed() {
+        let base = Duration::from_secs(10);
+        let duration = Duration::from_secs(40);
+        let previous = Duration::from_secs(20);
+        let cap = Duration::from_secs(50);
+        let sample = |jitter: Jitter, seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..100)
+                .map(|_| jitter.apply(&mut rng, duration, base, previous, cap))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(sample(Jitter::None, 1).iter().all(|d| *d == duration));
+        assert!(sample(Jitter::Full, 1).iter().all(|d| *d <= duration));
+        assert!(sample(Jitter::Equal, 1)
+            .iter()
+            .all(|d| *d >= duration / 2 && *d <= duration));
+        assert!(sample(Jitter::Decorrelated, 1)
+            .iter()
+            .all(|d| *d >= base && *d <= cap));
+
+        assert_eq!(sample(Jitter::Full, 7), sample(Jitter::Full, 7));
+        assert_ne!(sample(Jitter::Full, 7), sample(Jitter::Full, 8));
+    }
+
+    #[test]
+    fn decorrelated_jitter_stays_under_the_cap() {
+        let base = Duration::from_secs(10);
+        let cap = Duration::from_secs(20);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let duration =
+                Jitter::Decorrelated.apply(&mut rng, cap, base, Duration::from_secs(300), cap);
+            assert!(duration >= base && duration <= cap);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::watch;
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Source of time for a breaker: open timeouts, sliding windows, slow-call
+/// and call-timeout measurement, and the half-open `pause_time` all go
+/// through it.
+pub trait Clock: Send + Sync + 'static {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration) -> Sleep;
+
+    /// Wall-clock time matching `now()`, used to timestamp events.
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Tokio's clock. Follows `tokio::time::pause()` and `advance()`, so tests
+/// running on a paused runtime skip through open windows instantly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// A clock that only moves when `advance` is called. Sleeps complete as soon
+/// as the clock has been advanced past their deadline.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<watch::Sender<Instant>>,
+    // When the clock was created, to derive wall-clock time from `now`.
+    created: (Instant, SystemTime),
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        let created = (Instant::now(), SystemTime::now());
+        Self {
+            now: Arc::new(watch::Sender::new(created.0)),
+            created,
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.send_modify(|now| *now += duration);
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.borrow()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut now = self.now.subscribe();
+        let deadline = *now.borrow() + duration;
+        Box::pin(async move {
+            while *now.borrow_and_update() < deadline {
+                if now.changed().await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.created.1 + self.now().saturating_duration_since(self.created.0)
+    }
+}
@@ -0,0 +1,505 @@
+use crate::{Backoff, CallOutcome, Jitter, LagPolicy, PanicPolicy, SlidingWindow};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Validated breaker configuration, created through
+/// [`CircuitBreakerConfig::builder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    pub(crate) max_failures: Option<u32>,
+    pub(crate) failure_rate_threshold: Option<f64>,
+    pub(crate) sliding_window: SlidingWindow,
+    pub(crate) slow_call_duration_threshold: Option<Duration>,
+    pub(crate) slow_call_rate_threshold: Option<f64>,
+    pub(crate) minimum_calls: u32,
+    pub(crate) rolling_window: Duration,
+    pub(crate) timeout: Duration,
+    pub(crate) backoff: Option<Backoff>,
+    pub(crate) jitter: Jitter,
+    pub(crate) rng_seed: Option<u64>,
+    pub(crate) call_timeout: Option<Duration>,
+    pub(crate) timeout_counts_as_failure: bool,
+    pub(crate) dropped_permit_outcome: CallOutcome,
+    pub(crate) cancellation_outcome: CallOutcome,
+    pub(crate) panic_policy: PanicPolicy,
+    pub(crate) pause_time: Duration,
+    pub(crate) half_open_max_calls: u32,
+    pub(crate) half_open_success_threshold: u32,
+    pub(crate) event_capacity: usize,
+    pub(crate) lag_policy: LagPolicy,
+    pub(crate) shadow: bool,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: Some(5),
+            failure_rate_threshold: None,
+            sliding_window: SlidingWindow::default(),
+            slow_call_duration_threshold: None,
+            slow_call_rate_threshold: None,
+            minimum_calls: 10,
+            rolling_window: Duration::from_secs(60),
+            timeout: Duration::from_secs(60),
+            backoff: None,
+            jitter: Jitter::None,
+            rng_seed: None,
+            call_timeout: None,
+            timeout_counts_as_failure: true,
+            dropped_permit_outcome: CallOutcome::Ignored,
+            cancellation_outcome: CallOutcome::Ignored,
+            panic_policy: PanicPolicy::Propagate,
+            pause_time: Duration::ZERO,
+            half_open_max_calls: 1,
+            half_open_success_threshold: 1,
+            event_capacity: 16,
+            lag_policy: LagPolicy::default(),
+            shadow: false,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn builder() -> CircuitBreakerConfigBuilder {
+        CircuitBreakerConfigBuilder {
+            config: CircuitBreakerConfig::default(),
+        }
+    }
+
+    /// A builder starting from this configuration, for deriving variants.
+    pub fn to_builder(&self) -> CircuitBreakerConfigBuilder {
+        CircuitBreakerConfigBuilder {
+            config: self.clone(),
+        }
+    }
+
+    pub fn max_failures(&self) -> Option<u32> {
+        self.max_failures
+    }
+
+    pub fn failure_rate_threshold(&self) -> Option<f64> {
+        self.failure_rate_threshold
+    }
+
+    pub fn sliding_window(&self) -> SlidingWindow {
+        self.sliding_window
+    }
+
+    pub fn rolling_window(&self) -> Duration {
+        self.rolling_window
+    }
+
+    pub fn slow_call_duration_threshold(&self) -> Option<Duration> {
+        self.slow_call_duration_threshold
+    }
+
+    pub fn slow_call_rate_threshold(&self) -> Option<f64> {
+        self.slow_call_rate_threshold
+    }
+
+    pub fn minimum_calls(&self) -> u32 {
+        self.minimum_calls
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn backoff(&self) -> Option<Backoff> {
+        self.backoff
+    }
+
+    pub fn jitter(&self) -> Jitter {
+        self.jitter
+    }
+
+    pub fn rng_seed(&self) -> Option<u64> {
+        self.rng_seed
+    }
+
+    pub fn call_timeout(&self) -> Option<Duration> {
+        self.call_timeout
+    }
+
+    pub fn timeout_counts_as_failure(&self) -> bool {
+        self.timeout_counts_as_failure
+    }
+
+    pub fn dropped_permit_outcome(&self) -> CallOutcome {
+        self.dropped_permit_outcome
+    }
+
+    pub fn cancellation_outcome(&self) -> CallOutcome {
+        self.cancellation_outcome
+    }
+
+    pub fn panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+
+    pub fn pause_time(&self) -> Duration {
+        self.pause_time
+    }
+
+    pub fn half_open_max_calls(&self) -> u32 {
+        self.half_open_max_calls
+    }
+
+    pub fn half_open_success_threshold(&self) -> u32 {
+        self.half_open_success_threshold
+    }
+
+    pub fn event_capacity(&self) -> usize {
+        self.event_capacity
+    }
+
+    pub fn lag_policy(&self) -> LagPolicy {
+        self.lag_policy
+    }
+
+    pub fn shadow(&self) -> bool {
+        self.shadow
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfigBuilder {
+    config: CircuitBreakerConfig,
+}
+
+impl CircuitBreakerConfigBuilder {
+    /// Consecutive failures in the closed state that trip the breaker; `None`
+    /// disables the consecutive-failure rule.
+    pub fn max_failures(mut self, max_failures: impl Into<Option<u32>>) -> Self {
+        self.config.max_failures = max_failures.into();
+        self
+    }
+
+    /// Failure percentage (0-100] over the sliding window that trips the
+    /// breaker; `None` disables the failure-rate rule.
+    pub fn failure_rate_threshold(
+        mut self,
+        failure_rate_threshold: impl Into<Option<f64>>,
+    ) -> Self {
+        self.config.failure_rate_threshold = failure_rate_threshold.into();
+        self
+    }
+
+    /// Calls the failure and slow-call rates are computed over.
+    pub fn sliding_window(mut self, sliding_window: SlidingWindow) -> Self {
+        self.config.sliding_window = sliding_window;
+        self
+    }
+
+    /// Period the rolling failure and success counts in
+    /// `CircuitBreakerMetrics` cover. Unlike the sliding window it is never
+    /// cleared on transitions.
+    pub fn rolling_window(mut self, rolling_window: Duration) -> Self {
+        self.config.rolling_window = rolling_window;
+        self
+    }
+
+    /// Calls taking at least this long count as slow; `None` disables
+    /// slow-call detection.
+    pub fn slow_call_duration_threshold(
+        mut self,
+        slow_call_duration_threshold: impl Into<Option<Duration>>,
+    ) -> Self {
+        self.config.slow_call_duration_threshold = slow_call_duration_threshold.into();
+        self
+    }
+
+    /// Slow-call percentage (0-100] over the sliding window that trips the
+    /// breaker; `None` disables the slow-call-rate rule.
+    pub fn slow_call_rate_threshold(
+        mut self,
+        slow_call_rate_threshold: impl Into<Option<f64>>,
+    ) -> Self {
+        self.config.slow_call_rate_threshold = slow_call_rate_threshold.into();
+        self
+    }
+
+    /// Calls the window must hold before any rate is evaluated.
+    pub fn minimum_calls(mut self, minimum_calls: u32) -> Self {
+        self.config.minimum_calls = minimum_calls;
+        self
+    }
+
+    /// How long the breaker stays open before letting probe calls through.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Lengthens `timeout` on every reopen from half-open; `None` keeps the
+    /// open duration fixed.
+    pub fn backoff(mut self, backoff: impl Into<Option<Backoff>>) -> Self {
+        self.config.backoff = backoff.into();
+        self
+    }
+
+    /// Randomization applied to every open duration.
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.config.jitter = jitter;
+        self
+    }
+
+    /// Seed for the jitter RNG, for reproducible open durations in tests;
+    /// `None` seeds from OS entropy.
+    pub fn rng_seed(mut self, rng_seed: impl Into<Option<u64>>) -> Self {
+        self.config.rng_seed = rng_seed.into();
+        self
+    }
+
+    /// Upper bound on a single wrapped call; `None` lets calls run unbounded.
+    pub fn call_timeout(mut self, call_timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.call_timeout = call_timeout.into();
+        self
+    }
+
+    /// Whether a call cut off by `call_timeout` counts as a failure. When
+    /// `false` it is left out of the statistics entirely.
+    pub fn timeout_counts_as_failure(mut self, timeout_counts_as_failure: bool) -> Self {
+        self.config.timeout_counts_as_failure = timeout_counts_as_failure;
+        self
+    }
+
+    /// How a `Permit` dropped without a recorded outcome is counted.
+    pub fn dropped_permit_outcome(mut self, dropped_permit_outcome: CallOutcome) -> Self {
+        self.config.dropped_permit_outcome = dropped_permit_outcome;
+        self
+    }
+
+    /// How a call is counted when the future returned by `execute` is
+    /// dropped before the call finishes.
+    pub fn cancellation_outcome(mut self, cancellation_outcome: CallOutcome) -> Self {
+        self.config.cancellation_outcome = cancellation_outcome;
+        self
+    }
+
+    /// Whether `execute` catches panics in the wrapped call and records
+    /// them as failures.
+    pub fn panic_policy(mut self, panic_policy: PanicPolicy) -> Self {
+        self.config.panic_policy = panic_policy;
+        self
+    }
+
+    /// Pause after each half-open probe before its result is returned.
+    pub fn pause_time(mut self, pause_time: Duration) -> Self {
+        self.config.pause_time = pause_time;
+        self
+    }
+
+    /// Number of probe calls admitted while half-open.
+    pub fn half_open_max_calls(mut self, half_open_max_calls: u32) -> Self {
+        self.config.half_open_max_calls = half_open_max_calls;
+        self
+    }
+
+    /// Successful probes required to close the breaker again.
+    pub fn half_open_success_threshold(mut self, half_open_success_threshold: u32) -> Self {
+        self.config.half_open_success_threshold = half_open_success_threshold;
+        self
+    }
+
+    /// Capacity of the state-change channel; `0` disables event publication.
+    pub fn event_capacity(mut self, event_capacity: usize) -> Self {
+        self.config.event_capacity = event_capacity;
+        self
+    }
+
+    /// How subscribers handle events they fell behind on.
+    pub fn lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.config.lag_policy = lag_policy;
+        self
+    }
+
+    /// Runs the breaker in shadow mode: calls it would reject are let
+    /// through and reported instead. `ForcedOpen` still rejects.
+    pub fn shadow(mut self, shadow: bool) -> Self {
+        self.config.shadow = shadow;
+        self
+    }
+
+    pub fn build(self) -> Result<CircuitBreakerConfig, ConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+impl CircuitBreakerConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_failures == Some(0) {
+            return Err(ConfigError::Zero {
+                field: "max_failures",
+            });
+        }
+        for (field, value) in [
+            ("failure_rate_threshold", self.failure_rate_threshold),
+            ("slow_call_rate_threshold", self.slow_call_rate_threshold),
+        ] {
+            if let Some(value) = value {
+                if !(value > 0.0 && value <= 100.0) {
+                    return Err(ConfigError::InvalidPercentage { field, value });
+                }
+            }
+        }
+        let rates_enabled =
+            self.failure_rate_threshold.is_some() || self.slow_call_rate_threshold.is_some();
+        if self.max_failures.is_none() && !rates_enabled {
+            return Err(ConfigError::NoTrippingRule);
+        }
+        match self.sliding_window {
+            SlidingWindow::Count(0) => {
+                return Err(ConfigError::Zero {
+                    field: "sliding_window",
+                })
+            }
+            SlidingWindow::Count(size) if self.minimum_calls > size && rates_enabled => {
+                return Err(ConfigError::UnreachableMinimumCalls {
+                    minimum_calls: self.minimum_calls,
+                    window_size: size,
+                })
+            }
+            SlidingWindow::Time { window, buckets } if window.is_zero() || buckets == 0 => {
+                return Err(ConfigError::Zero {
+                    field: "sliding_window",
+                })
+            }
+            _ => {}
+        }
+        if self.slow_call_rate_threshold.is_some() && self.slow_call_duration_threshold.is_none() {
+            return Err(ConfigError::MissingSlowCallDuration);
+        }
+        for (field, duration) in [
+            ("timeout", Some(self.timeout)),
+            ("rolling_window", Some(self.rolling_window)),
+            ("call_timeout", self.call_timeout),
+            (
+                "slow_call_duration_threshold",
+                self.slow_call_duration_threshold,
+            ),
+        ] {
+            if duration.is_some_and(|duration| duration.is_zero()) {
+                return Err(ConfigError::Zero { field });
+            }
+        }
+        if let Some(backoff) = self.backoff {
+            if !(backoff.multiplier.is_finite() && backoff.multiplier >= 1.0) {
+                return Err(ConfigError::InvalidMultiplier {
+                    value: backoff.multiplier,
+                });
+            }
+            if backoff.max_timeout < self.timeout {
+                return Err(ConfigError::MaxTimeoutBelowTimeout {
+                    max_timeout: backoff.max_timeout,
+                    timeout: self.timeout,
+                });
+            }
+        }
+        if self.half_open_max_calls == 0 {
+            return Err(ConfigError::Zero {
+                field: "half_open_max_calls",
+            });
+        }
+        if self.half_open_success_threshold == 0 {
+            return Err(ConfigError::Zero {
+                field: "half_open_success_threshold",
+            });
+        }
+        if self.half_open_success_threshold > self.half_open_max_calls {
+            return Err(ConfigError::UnreachableSuccessThreshold {
+                success_threshold: self.half_open_success_threshold,
+                max_calls: self.half_open_max_calls,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why a [`CircuitBreakerConfigBuilder`] refused to build.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A count or duration that has to be non-zero was zero.
+    Zero { field: &'static str },
+    /// `max_failures`, `failure_rate_threshold` and
+    /// `slow_call_rate_threshold` are all disabled, so the breaker could
+    /// never open.
+    NoTrippingRule,
+    /// A percentage threshold was outside `(0, 100]`.
+    InvalidPercentage { field: &'static str, value: f64 },
+    /// `minimum_calls` is larger than a count-based window can ever hold.
+    UnreachableMinimumCalls {
+        minimum_calls: u32,
+        window_size: u32,
+    },
+    /// `slow_call_rate_threshold` was set without a
+    /// `slow_call_duration_threshold` to classify calls as slow.
+    MissingSlowCallDuration,
+    /// The backoff multiplier was below 1 or not a finite number.
+    InvalidMultiplier { value: f64 },
+    /// The backoff cap is shorter than the base open `timeout`.
+    MaxTimeoutBelowTimeout {
+        max_timeout: Duration,
+        timeout: Duration,
+    },
+    /// More successful probes are required than half-open admits.
+    UnreachableSuccessThreshold {
+        success_threshold: u32,
+        max_calls: u32,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Zero { field } => write!(f, "`{}` must be greater than zero", field),
+            ConfigError::NoTrippingRule => write!(
+                f,
+                "no tripping rule is enabled; set `max_failures`, `failure_rate_threshold` or `slow_call_rate_threshold`"
+            ),
+            ConfigError::InvalidPercentage { field, value } => write!(
+                f,
+                "`{}` must be a percentage in (0, 100], got {}",
+                field, value
+            ),
+            ConfigError::UnreachableMinimumCalls {
+                minimum_calls,
+                window_size,
+            } => write!(
+                f,
+                "`minimum_calls` ({}) exceeds the sliding window size ({}), so rates would never be evaluated",
+                minimum_calls, window_size
+            ),
+            ConfigError::MissingSlowCallDuration => write!(
+                f,
+                "`slow_call_rate_threshold` requires `slow_call_duration_threshold` to be set"
+            ),
+            ConfigError::InvalidMultiplier { value } => write!(
+                f,
+                "backoff `multiplier` must be a finite number of at least 1, got {}",
+                value
+            ),
+            ConfigError::MaxTimeoutBelowTimeout {
+                max_timeout,
+                timeout,
+            } => write!(
+                f,
+                "backoff `max_timeout` ({:?}) is shorter than `timeout` ({:?})",
+                max_timeout, timeout
+            ),
+            ConfigError::UnreachableSuccessThreshold {
+                success_threshold,
+                max_calls,
+            } => write!(
+                f,
+                "`half_open_success_threshold` ({}) exceeds `half_open_max_calls` ({}), so the breaker could never close",
+                success_threshold, max_calls
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,356 @@
+use crate::{CircuitBreakerMetrics, CircuitBreakerState};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Instant, SystemTime};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::Stream;
+
+/// Why the breaker changed state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TransitionReason {
+    /// `max_failures` consecutive failures were seen while closed.
+    ConsecutiveFailures,
+    /// The failure rate over the sliding window reached
+    /// `failure_rate_threshold`.
+    FailureRate,
+    /// The slow-call rate over the sliding window reached
+    /// `slow_call_rate_threshold`.
+    SlowCallRate,
+    /// The open timeout elapsed and the next call was let through as a probe.
+    OpenTimeoutElapsed,
+    /// A half-open probe failed.
+    ProbeFailed,
+    /// Enough half-open probes succeeded.
+    ProbesSucceeded,
+    /// `trip()` or `reset()` was called directly.
+    Manual,
+    /// An operator override such as `force_open()` was set.
+    Override,
+    /// `release()` ended an operator override.
+    OverrideReleased,
+    /// A breaker in shadow mode let through a call it would have rejected.
+    /// Published for the first such call after each state change only; the
+    /// rest are counted in `total_shadow_rejections`. The state is
+    /// unchanged, so `from` and `to` are equal.
+    ShadowRejected,
+    /// The configuration was replaced with `update_config`. The state is
+    /// unchanged, so `from` and `to` are equal.
+    ConfigChanged,
+}
+
+/// A state transition, configuration change or shadow-mode rejection,
+/// published to `subscribe()` streams. `set_on_*` callbacks only see
+/// transitions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerEvent {
+    pub from: CircuitBreakerState,
+    pub to: CircuitBreakerState,
+    pub at: Instant,
+    /// Wall-clock time of the event from the breaker's `Clock`, for logs and
+    /// alerting outside the process.
+    pub timestamp: SystemTime,
+    pub reason: TransitionReason,
+    /// Counters as they were right before the transition.
+    pub metrics: CircuitBreakerMetrics,
+}
+
+impl CircuitBreakerEvent {
+    /// Whether the event reports a state transition rather than a
+    /// configuration change or a shadow-mode rejection.
+    pub fn is_transition(&self) -> bool {
+        !matches!(
+            self.reason,
+            TransitionReason::ConfigChanged | TransitionReason::ShadowRejected
+        )
+    }
+}
+
+/// What a subscriber does when it falls behind and the channel overwrites
+/// events it has not read yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum LagPolicy {
+    /// Skip the missed events silently.
+    Drop,
+    /// Skip the missed events and report how many were lost on stderr.
+    Log,
+    /// Skip the missed events and add them to `CircuitBreaker::lagged_events`.
+    #[default]
+    Count,
+}
+
+struct LagTracker {
+    policy: LagPolicy,
+    lagged: AtomicU64,
+}
+
+impl LagTracker {
+    fn record(&self, missed: u64) {
+        match self.policy {
+            LagPolicy::Drop => {}
+            LagPolicy::Log => eprintln!(
+                "Circuit breaker subscriber lagged, {} events dropped",
+                missed
+            ),
+            LagPolicy::Count => {
+                self.lagged.fetch_add(missed, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Stream of [`CircuitBreakerEvent`]s returned by `CircuitBreaker::subscribe`.
+///
+/// Events missed because the subscriber lagged are handled per the breaker's
+/// [`LagPolicy`]; the stream itself only ends once the breaker is dropped.
+pub struct EventStream {
+    inner: Option<BroadcastStream<CircuitBreakerEvent>>,
+    lag: Arc<LagTracker>,
+}
+
+impl EventStream {
+    pub async fn recv(&mut self) -> Option<CircuitBreakerEvent> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+}
+
+impl Stream for EventStream {
+    type Item = CircuitBreakerEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        loop {
+            match Pin::new(&mut *inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => return Poll::Ready(Some(event)),
+                Poll::Ready(Some(Err(BroadcastStreamRecvError::Lagged(missed)))) => {
+                    this.lag.record(missed)
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// Publishing side of the state-change channel. Publishing never fails: with
+// events disabled or nobody subscribed the event is simply discarded.
+pub(crate) struct EventBus {
+    sender: Option<broadcast::Sender<CircuitBreakerEvent>>,
+    lag: Arc<LagTracker>,
+}
+
+impl EventBus {
+    pub(crate) fn new(capacity: usize, policy: LagPolicy) -> Self {
+        let sender = (capacity > 0).then(|| broadcast::channel(capacity).0);
+        Self {
+            sender,
+            lag: Arc::new(LagTracker {
+                policy,
+                lagged: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub(crate) fn publish(&self, event: CircuitBreakerEvent) {
+        if let Some(sender) = &self.sender {
+            // An error only means there are no receivers right now.
+            let _ = sender.send(event);
+        }
+    }
+
+    pub(crate) fn lagged(&self) -> u64 {
+        self.lag.lagged.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn subscribe(&self) -> EventStream {
+        EventStream {
+            inner: self
+                .sender
+                .as_ref()
+                .map(|sender| BroadcastStream::new(sender.subscribe())),
+            lag: Arc::clone(&self.lag),
+        }
+    }
+
+    // Runs `callback` for every transition into `state` until the breaker is
+    // dropped.
+    pub(crate) fn listen<F>(&self, state: CircuitBreakerState, mut callback: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        if self.sender.is_none() {
+            return;
+        }
+        let mut events = self.subscribe();
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if event.to == state && event.is_transition() {
+                    callback();
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CircuitBreaker, CircuitBreakerConfig, Clock, MockClock};
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    fn breaker(capacity: usize, policy: LagPolicy) -> CircuitBreaker {
+        CircuitBreaker::with_config(
+            CircuitBreakerConfig::builder()
+                .event_capacity(capacity)
+                .lag_policy(policy)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    // Opens and closes the breaker `times` times: two events each.
+    fn flap(breaker: &CircuitBreaker, times: usize) {
+        for _ in 0..times {
+            breaker.trip();
+            breaker.reset();
+        }
+    }
+
+    // Lets spawned listeners run up to their next await.
+    async fn settle() {
+        for _ in 0..5 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn lagged_subscriber_skips_missed_events_and_keeps_going() {
+        let breaker = breaker(2, LagPolicy::Count);
+        let mut events = breaker.subscribe();
+        flap(&breaker, 3);
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.to, CircuitBreakerState::Open);
+        assert_eq!(breaker.lagged_events(), 4);
+        assert_eq!(events.recv().await.unwrap().to, CircuitBreakerState::Closed);
+
+        breaker.trip();
+        assert_eq!(events.recv().await.unwrap().to, CircuitBreakerState::Open);
+        assert_eq!(breaker.lagged_events(), 4);
+    }
+
+    #[tokio::test]
+    async fn drop_policy_does_not_count_missed_events() {
+        let breaker = breaker(1, LagPolicy::Drop);
+        let mut events = breaker.subscribe();
+        flap(&breaker, 2);
+        assert_eq!(events.recv().await.unwrap().to, CircuitBreakerState::Closed);
+        assert_eq!(breaker.lagged_events(), 0);
+    }
+
+    #[tokio::test]
+    async fn callbacks_survive_a_lagged_channel() {
+        let breaker = breaker(1, LagPolicy::Count);
+        let opened = Arc::new(AtomicUsize::new(0));
+        breaker.set_on_open({
+            let opened = Arc::clone(&opened);
+            move || {
+                opened.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        // The listener can't run in between, so it misses all but the last.
+        flap(&breaker, 3);
+        breaker.trip();
+        settle().await;
+        assert_eq!(opened.load(Ordering::SeqCst), 1);
+        assert!(breaker.lagged_events() > 0);
+
+        breaker.reset();
+        settle().await;
+        breaker.trip();
+        settle().await;
+        assert_eq!(opened.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn zero_capacity_disables_events() {
+        let breaker = breaker(0, LagPolicy::Count);
+        let opened = Arc::new(AtomicUsize::new(0));
+        breaker.set_on_open({
+            let opened = Arc::clone(&opened);
+            move || {
+                opened.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let mut events = breaker.subscribe();
+
+        breaker.trip();
+        settle().await;
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        assert_eq!(events.recv().await, None);
+        assert_eq!(opened.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn events_describe_the_transition() {
+        let clock = MockClock::new();
+        let breaker = CircuitBreaker::with_clock(
+            CircuitBreakerConfig::builder()
+                .max_failures(2)
+                .build()
+                .unwrap(),
+            clock.clone(),
+        );
+        let mut events = breaker.subscribe();
+        clock.advance(Duration::from_secs(90));
+        for _ in 0..2 {
+            let _ = breaker.execute(|| async { Err::<(), _>(()) }).await;
+        }
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.from, CircuitBreakerState::Closed);
+        assert_eq!(event.to, CircuitBreakerState::Open);
+        assert_eq!(event.reason, TransitionReason::ConsecutiveFailures);
+        assert_eq!(event.at, clock.now());
+        assert_eq!(event.timestamp, clock.system_time());
+        assert!(event.is_transition());
+        // Counters as they were when the breaker tripped.
+        assert_eq!(event.metrics.consecutive_failures, 2);
+        assert_eq!(event.metrics.total_failures, 2);
+    }
+
+    #[tokio::test]
+    async fn config_changes_are_not_transitions() {
+        let breaker = breaker(16, LagPolicy::Count);
+        let mut events = breaker.subscribe();
+        breaker.update_config(
+            CircuitBreakerConfig::builder()
+                .max_failures(3)
+                .build()
+                .unwrap(),
+        );
+        breaker.trip();
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reason, TransitionReason::ConfigChanged);
+        assert_eq!(event.from, event.to);
+        assert!(!event.is_transition());
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reason, TransitionReason::Manual);
+        assert!(event.is_transition());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{CallOutcome, CircuitBreaker, CircuitBreakerError};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type FallbackFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, CircuitBreakerError<E>>> + Send>>;
+
+type Fallback<T, E> = dyn Fn(CircuitBreakerError<E>) -> FallbackFuture<T, E> + Send + Sync;
+
+/// A breaker paired with a default fallback, returned by
+/// `CircuitBreaker::with_fallback`.
+///
+/// Every call that is rejected, times out or fails is handed to the fallback
+/// with the reason, and the fallback's result is returned instead.
+pub struct FallbackBreaker<T, E> {
+    breaker: CircuitBreaker,
+    fallback: Arc<Fallback<T, E>>,
+}
+
+impl<T, E> Clone for FallbackBreaker<T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            breaker: self.breaker.clone(),
+            fallback: Arc::clone(&self.fallback),
+        }
+    }
+}
+
+impl<T, E> FallbackBreaker<T, E> {
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    pub async fn execute<F, Fut>(&self, func: F) -> Result<T, CircuitBreakerError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.execute_with_classifier(func, CallOutcome::from_result)
+            .await
+    }
+
+    pub async fn execute_with_classifier<F, Fut, C>(
+        &self,
+        func: F,
+        classify: C,
+    ) -> Result<T, CircuitBreakerError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        C: FnOnce(&Result<T, E>) -> CallOutcome,
+    {
+        match self.breaker.execute_with_classifier(func, classify).await {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.breaker.count_fallback();
+                (self.fallback)(err).await
+            }
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Like `execute`, but a call that is rejected, times out or fails is
+    /// handed to `fallback` with the reason, and the fallback's result is
+    /// returned instead. Returning `Err(err)` from the fallback passes the
+    /// original error on.
+    pub async fn execute_with_fallback<F, Fut, T, E, FB, FbFut>(
+        &self,
+        func: F,
+        fallback: FB,
+    ) -> Result<T, CircuitBreakerError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        FB: FnOnce(CircuitBreakerError<E>) -> FbFut,
+        FbFut: Future<Output = Result<T, CircuitBreakerError<E>>>,
+    {
+        match self.execute(func).await {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.count_fallback();
+                fallback(err).await
+            }
+        }
+    }
+
+    /// Pairs the breaker with a default fallback used by every call made
+    /// through the returned `FallbackBreaker`.
+    pub fn with_fallback<T, E, FB, FbFut>(&self, fallback: FB) -> FallbackBreaker<T, E>
+    where
+        FB: Fn(CircuitBreakerError<E>) -> FbFut + Send + Sync + 'static,
+        FbFut: Future<Output = Result<T, CircuitBreakerError<E>>> + Send + 'static,
+    {
+        FallbackBreaker {
+            breaker: self.clone(),
+            fallback: Arc::new(move |err| Box::pin(fallback(err))),
+        }
+    }
+
+    pub(crate) fn count_fallback(&self) {
+        let mut core = self.core();
+        core.total_fallbacks = core.total_fallbacks.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CircuitBreakerConfig, CircuitBreakerState, MockClock};
+    use std::future::pending;
+    use std::time::Duration;
+
+    type Error = CircuitBreakerError<&'static str>;
+
+    fn breaker() -> (CircuitBreaker, MockClock) {
+        let clock = MockClock::new();
+        let config = CircuitBreakerConfig::builder()
+            .max_failures(2)
+            .call_timeout(Duration::from_secs(1))
+            .build()
+            .unwrap();
+        (CircuitBreaker::with_clock(config, clock.clone()), clock)
+    }
+
+    fn reason(err: Error) -> &'static str {
+        match err {
+            CircuitBreakerError::Open => "open",
+            CircuitBreakerError::Timeout => "timeout",
+            CircuitBreakerError::Inner(err) => err,
+            CircuitBreakerError::Panicked(_) => "panicked",
+        }
+    }
+
+    // Lets spawned tasks run up to their next await.
+    async fn settle() {
+        for _ in 0..5 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_with_fallback_is_told_why_the_call_failed() {
+        let (breaker, clock) = breaker();
+        let fallback = |err| async move { Ok::<_, Error>(reason(err)) };
+
+        let result = breaker
+            .execute_with_fallback(|| async { Ok("live") }, fallback)
+            .await;
+        assert_eq!(result.unwrap(), "live");
+        let result = breaker
+            .execute_with_fallback(|| async { Err("down") }, fallback)
+            .await;
+        assert_eq!(result.unwrap(), "down");
+
+        let call = tokio::spawn({
+            let breaker = breaker.clone();
+            async move {
+                breaker
+                    .execute_with_fallback(pending::<Result<&str, &str>>, fallback)
+                    .await
+            }
+        });
+        settle().await;
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(call.await.unwrap().unwrap(), "timeout");
+
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        let result = breaker
+            .execute_with_fallback(|| async { Ok("live") }, fallback)
+            .await;
+        assert_eq!(result.unwrap(), "open");
+        assert_eq!(breaker.metrics().total_fallbacks, 3);
+    }
+
+    #[tokio::test]
+    async fn fallback_errors_are_returned() {
+        let (breaker, _) = breaker();
+        let result = breaker
+            .execute_with_fallback(
+                || async { Err::<(), _>("down") },
+                |err| async move { Err(err) },
+            )
+            .await;
+        assert!(matches!(result, Err(CircuitBreakerError::Inner("down"))));
+
+        let fallback = breaker.with_fallback(|err: Error| async move { Err::<(), _>(err) });
+        fallback.breaker().trip();
+        assert!(fallback
+            .execute(|| async { Ok(()) })
+            .await
+            .unwrap_err()
+            .is_open());
+        assert_eq!(breaker.metrics().total_fallbacks, 2);
+    }
+
+    #[tokio::test]
+    async fn fallback_breaker_answers_every_failed_call() {
+        let (breaker, clock) = breaker();
+        let fallback = breaker.with_fallback(|err| async move { Ok(reason(err)) });
+
+        assert_eq!(
+            fallback.execute(|| async { Ok("live") }).await.unwrap(),
+            "live"
+        );
+        assert_eq!(
+            fallback.execute(|| async { Err("down") }).await.unwrap(),
+            "down"
+        );
+
+        let call = tokio::spawn({
+            let fallback = fallback.clone();
+            async move { fallback.execute(pending::<Result<&str, &str>>).await }
+        });
+        settle().await;
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(call.await.unwrap().unwrap(), "timeout");
+
+        assert_eq!(
+            fallback.execute(|| async { Ok("live") }).await.unwrap(),
+            "open"
+        );
+        assert_eq!(breaker.metrics().total_fallbacks, 3);
+    }
+}
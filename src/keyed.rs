@@ -0,0 +1,281 @@
+use crate::lru::Lru;
+use crate::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerError, CircuitBreakerState, Clock,
+    ConfigError, SystemClock,
+};
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// One breaker per key, such as a host or a tenant, created on first use
+/// from a shared configuration.
+///
+/// With [`KeyLimits`], breakers for keys that have not been used for
+/// `idle_timeout` are evicted, and with `max_keys` set the least recently
+/// used key makes room for a new one, so the number of breakers stays
+/// bounded even when keys come from user input.
+pub struct KeyedCircuitBreaker<K> {
+    inner: Arc<KeyedInner<K>>,
+}
+
+impl<K> Clone for KeyedCircuitBreaker<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// Bounds on the breakers a [`KeyedCircuitBreaker`] keeps, fixed when it is
+/// created. The default keeps every key forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyLimits {
+    /// Evict breakers whose key has not been used for this long, or never
+    /// with `None`. Must not be zero.
+    pub idle_timeout: Option<Duration>,
+    /// Cap the number of breakers, evicting the least recently used key
+    /// when a new one arrives, or no limit with `None`. Must not be zero.
+    pub max_keys: Option<usize>,
+}
+
+impl KeyLimits {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.idle_timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(ConfigError::Zero {
+                field: "idle_timeout",
+            });
+        }
+        if self.max_keys == Some(0) {
+            return Err(ConfigError::Zero { field: "max_keys" });
+        }
+        Ok(())
+    }
+}
+
+struct KeyedInner<K> {
+    config: CircuitBreakerConfig,
+    limits: KeyLimits,
+    clock: Arc<dyn Clock>,
+    breakers: Mutex<Lru<K, CircuitBreaker>>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedCircuitBreaker<K> {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+
+    /// Every breaker created for a key shares `clock`.
+    pub fn with_clock<C: Clock>(config: CircuitBreakerConfig, clock: C) -> Self {
+        Self::build(config, KeyLimits::default(), Arc::new(clock))
+    }
+
+    /// Like `new`, bounding the breakers kept with `limits`. Returns an
+    /// error if either limit is zero.
+    pub fn with_limits(
+        config: CircuitBreakerConfig,
+        limits: KeyLimits,
+    ) -> Result<Self, ConfigError> {
+        Self::with_limits_and_clock(config, limits, SystemClock)
+    }
+
+    /// Like `with_clock`, bounding the breakers kept with `limits`. Returns
+    /// an error if either limit is zero.
+    pub fn with_limits_and_clock<C: Clock>(
+        config: CircuitBreakerConfig,
+        limits: KeyLimits,
+        clock: C,
+    ) -> Result<Self, ConfigError> {
+        limits.validate()?;
+        Ok(Self::build(config, limits, Arc::new(clock)))
+    }
+
+    fn build(config: CircuitBreakerConfig, limits: KeyLimits, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Arc::new(KeyedInner {
+                config,
+                limits,
+                clock,
+                breakers: Mutex::new(Lru::new()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &CircuitBreakerConfig {
+        &self.inner.config
+    }
+
+    pub fn limits(&self) -> KeyLimits {
+        self.inner.limits
+    }
+
+    pub async fn execute_keyed<Q, F, Fut, T, E>(
+        &self,
+        key: &Q,
+        func: F,
+    ) -> Result<T, CircuitBreakerError<E>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.breaker(key).execute(func).await
+    }
+
+    /// The breaker for `key`, created if needed. Counts as a use of the key.
+    pub fn breaker<Q>(&self, key: &Q) -> CircuitBreaker
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let now = self.inner.clock.now();
+        let mut breakers = self.breakers();
+        self.evict_idle_locked(&mut breakers, now);
+        if let Some(breaker) = breakers.touch(key, now) {
+            return breaker.clone();
+        }
+
+        let breaker = CircuitBreaker::with_shared_clock(
+            self.inner.config.clone(),
+            Arc::clone(&self.inner.clock),
+        );
+        breakers.insert(
+            key.to_owned(),
+            breaker.clone(),
+            now,
+            self.inner.limits.max_keys,
+        );
+        breaker
+    }
+
+    /// The breaker for `key` if it exists, without counting as a use.
+    pub fn get<Q>(&self, key: &Q) -> Option<CircuitBreaker>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.breakers().get(key).map(|(breaker, _)| breaker.clone())
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<CircuitBreaker>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.breakers().remove(key)
+    }
+
+    /// Idle keys are otherwise only evicted when another key is used; call
+    /// this periodically to free them on a quiet container.
+    pub fn evict_idle(&self) {
+        let now = self.inner.clock.now();
+        self.evict_idle_locked(&mut self.breakers(), now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.breakers().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakers().is_empty()
+    }
+
+    /// Every key with the state of its breaker, least recently used first.
+    pub fn states(&self) -> Vec<(K, CircuitBreakerState)> {
+        self.breakers()
+            .iter()
+            .map(|(key, breaker)| (key.clone(), breaker.state()))
+            .collect()
+    }
+
+    fn evict_idle_locked(&self, breakers: &mut Lru<K, CircuitBreaker>, now: Instant) {
+        if let Some(idle_timeout) = self.inner.limits.idle_timeout {
+            breakers.remove_unused(now, idle_timeout);
+        }
+    }
+
+    fn breakers(&self) -> MutexGuard<'_, Lru<K, CircuitBreaker>> {
+        self.inner
+            .breakers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+
+    fn keyed(limits: KeyLimits) -> (KeyedCircuitBreaker<String>, MockClock) {
+        let clock = MockClock::new();
+        let keyed = KeyedCircuitBreaker::with_limits_and_clock(
+            CircuitBreakerConfig::default(),
+            limits,
+            clock.clone(),
+        )
+        .unwrap();
+        (keyed, clock)
+    }
+
+    fn keys(keyed: &KeyedCircuitBreaker<String>) -> Vec<String> {
+        keyed.states().into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn max_keys_evicts_least_recently_used() {
+        let (keyed, _) = keyed(KeyLimits {
+            idle_timeout: None,
+            max_keys: Some(2),
+        });
+        keyed.breaker("a").trip();
+        keyed.breaker("b");
+        keyed.breaker("a");
+        keyed.breaker("c");
+        assert_eq!(keys(&keyed), ["a", "c"]);
+        assert_eq!(keyed.get("a").unwrap().state(), CircuitBreakerState::Open);
+
+        // `get` does not count as a use.
+        keyed.get("a");
+        keyed.breaker("d");
+        assert_eq!(keys(&keyed), ["c", "d"]);
+    }
+
+    #[test]
+    fn idle_keys_are_evicted() {
+        let (keyed, clock) = keyed(KeyLimits {
+            idle_timeout: Some(Duration::from_secs(10)),
+            max_keys: None,
+        });
+        keyed.breaker("a");
+        clock.advance(Duration::from_secs(6));
+        keyed.breaker("b");
+        clock.advance(Duration::from_secs(6));
+        keyed.evict_idle();
+        assert_eq!(keys(&keyed), ["b"]);
+
+        clock.advance(Duration::from_secs(6));
+        keyed.breaker("c");
+        assert_eq!(keys(&keyed), ["c"]);
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        for limits in [
+            KeyLimits {
+                idle_timeout: Some(Duration::ZERO),
+                max_keys: None,
+            },
+            KeyLimits {
+                idle_timeout: None,
+                max_keys: Some(0),
+            },
+        ] {
+            let keyed =
+                KeyedCircuitBreaker::<String>::with_limits(CircuitBreakerConfig::default(), limits);
+            assert!(matches!(keyed, Err(ConfigError::Zero { .. })));
+        }
+    }
+}
@@ -1,156 +1,1538 @@
+mod backoff;
+mod clock;
+mod config;
+mod event;
+mod fallback;
+mod keyed;
+mod lru;
+mod metrics;
+mod panic;
+mod permit;
+mod registry;
+#[cfg(feature = "serde")]
+mod settings;
+mod stale;
+#[cfg(feature = "serde")]
+mod watch;
+mod window;
+
+pub use backoff::{Backoff, Jitter};
+pub use clock::{Clock, MockClock, Sleep, SystemClock};
+pub use config::{CircuitBreakerConfig, CircuitBreakerConfigBuilder, ConfigError};
+pub use event::{CircuitBreakerEvent, EventStream, LagPolicy, TransitionReason};
+pub use fallback::FallbackBreaker;
+pub use keyed::{KeyLimits, KeyedCircuitBreaker};
+pub use metrics::CircuitBreakerMetrics;
+pub use panic::{PanicPayload, PanicPolicy};
+pub use permit::Permit;
+pub use registry::CircuitBreakerRegistry;
+#[cfg(feature = "serde")]
+pub use settings::{
+    BackoffSettings, CircuitBreakerFile, CircuitBreakerSettings, Format, HumanDuration, LoadError,
+    Toggle, WindowSettings,
+};
+pub use stale::{Cached, StaleCache};
+#[cfg(feature = "serde")]
+pub use watch::ConfigWatcher;
+pub use window::SlidingWindow;
+
+use event::EventBus;
+use window::{Sample, Window};
+
 // Import necessary modules
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::{Duration, Instant};
-use tokio::sync::broadcast;
-use tokio::time::sleep;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CircuitBreakerState {
     Closed,
     Open,
     HalfOpen,
+    /// Operator override: every call is rejected until `release()`.
+    ForcedOpen,
+    /// Operator override: every call goes through and is only counted in the
+    /// lifetime totals.
+    ForcedClosed,
+    /// Operator override: every call goes through and nothing is recorded.
+    Disabled,
+    /// Operator override: every call goes through and is recorded as if the
+    /// breaker were closed, but the tripping rules never open it.
+    MetricsOnly,
+}
+
+impl CircuitBreakerState {
+    /// Whether the state was set by an operator and is left alone by
+    /// automatic transitions, `trip()` and `reset()`.
+    pub fn is_override(self) -> bool {
+        matches!(
+            self,
+            CircuitBreakerState::ForcedOpen
+                | CircuitBreakerState::ForcedClosed
+                | CircuitBreakerState::Disabled
+                | CircuitBreakerState::MetricsOnly
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CircuitBreakerError<E> {
+    /// The breaker rejected the call without running it.
+    Open,
+    /// The wrapped call did not finish within `call_timeout`.
+    Timeout,
+    /// The wrapped call ran and returned an error.
+    Inner(E),
+    /// The wrapped call panicked and `panic_policy` is `PanicPolicy::Return`.
+    Panicked(PanicPayload),
+}
+
+impl<E> CircuitBreakerError<E> {
+    pub fn is_open(&self) -> bool {
+        matches!(self, CircuitBreakerError::Open)
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, CircuitBreakerError::Timeout)
+    }
+
+    pub fn is_panicked(&self) -> bool {
+        matches!(self, CircuitBreakerError::Panicked(_))
+    }
+
+    pub fn into_inner(self) -> Option<E> {
+        match self {
+            CircuitBreakerError::Inner(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitBreakerError::Open => write!(f, "Circuit breaker is open"),
+            CircuitBreakerError::Timeout => write!(f, "Call timed out"),
+            CircuitBreakerError::Inner(err) => write!(f, "{}", err),
+            CircuitBreakerError::Panicked(payload) => match payload.message() {
+                Some(message) => write!(f, "Call panicked: {}", message),
+                None => write!(f, "Call panicked"),
+            },
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for CircuitBreakerError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CircuitBreakerError::Inner(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
+/// How a finished call is accounted for by the breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum CallOutcome {
+    /// Counts like `handle_success`.
+    Success,
+    /// Counts like `handle_failure`.
+    Failure,
+    /// Left out of the statistics; a half-open probe slot is given back.
+    Ignored,
+}
+
+impl CallOutcome {
+    /// The default classification: `Ok` succeeds and `Err` fails.
+    pub fn from_result<T, E>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => CallOutcome::Success,
+            Err(_) => CallOutcome::Failure,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct CircuitBreaker {
-    pub state: CircuitBreakerState,
-    pub consecutive_failures: u32,
-    pub total_failures: u32,
-    pub total_successes: u32,
-    pub max_failures: u32,
-    pub timeout: Duration,
-    pub open_timeout: Instant,
-    pub pause_time: Duration,
-    pub consecutive_successes: u32,
-    sender: broadcast::Sender<String>,
+    inner: Arc<Inner>,
+}
+
+// Clones of a breaker are shared across tasks and threads, so the handle and
+// the futures it returns must stay `Send` (and the handle `Sync`).
+const _: () = {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[allow(dead_code)]
+    fn assertions(breaker: &CircuitBreaker) {
+        assert_send_sync::<CircuitBreaker>();
+        assert_send(&breaker.execute(|| async { Ok::<(), ()>(()) }));
+    }
+};
+
+struct Inner {
+    clock: Arc<dyn Clock>,
+    core: Mutex<Core>,
+    events: EventBus,
+}
+
+// Mutable state shared by every clone of a breaker. The lock is only ever
+// held for bookkeeping and never across the awaited call.
+struct Core {
+    // Swapped as a whole by `update_config`; calls keep the snapshot they
+    // were admitted with.
+    config: Arc<CircuitBreakerConfig>,
+    state: CircuitBreakerState,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    total_failures: u64,
+    total_successes: u64,
+    total_slow_calls: u64,
+    total_timeouts: u64,
+    total_shadow_rejections: u64,
+    total_cancellations: u64,
+    total_fallbacks: u64,
+    open_timeout: Instant,
+    // Probe calls admitted in the current half-open period.
+    half_open_calls: u32,
+    // Whether a shadow-mode rejection has been published in the current
+    // period; later ones are only counted.
+    shadow_reported: bool,
+    // Bumped on every state change so outcomes of calls admitted under an
+    // earlier state can be told apart from current ones.
+    period: u64,
+    // Consecutive reopens from half-open, driving the backoff exponent.
+    reopens: u32,
+    // When the breaker last closed.
+    closed_since: Instant,
+    // Length of the most recent open period, after jitter.
+    open_duration: Duration,
+    rng: StdRng,
+    // Outcomes of recent calls while closed.
+    window: Window,
+    // Outcomes of every call during the last `rolling_window`, kept across
+    // transitions for reporting.
+    rolling: Window,
+}
+
+// Slices the rolling reporting window is split into.
+const ROLLING_BUCKETS: u32 = 12;
+
+fn rolling_window(config: &CircuitBreakerConfig) -> SlidingWindow {
+    SlidingWindow::Time {
+        window: config.rolling_window,
+        buckets: ROLLING_BUCKETS,
+    }
+}
+
+impl Core {
+    fn metrics(&self, now: Instant) -> CircuitBreakerMetrics {
+        let window = self.window.stats(now);
+        let rolling = self.rolling.stats(now);
+        CircuitBreakerMetrics {
+            consecutive_failures: self.consecutive_failures,
+            consecutive_successes: self.consecutive_successes,
+            total_failures: self.total_failures,
+            total_successes: self.total_successes,
+            total_slow_calls: self.total_slow_calls,
+            total_timeouts: self.total_timeouts,
+            total_shadow_rejections: self.total_shadow_rejections,
+            total_cancellations: self.total_cancellations,
+            total_fallbacks: self.total_fallbacks,
+            rolling_failures: rolling.failures,
+            rolling_successes: rolling.calls - rolling.failures,
+            rolling_slow_calls: rolling.slow_calls,
+            window_calls: window.calls,
+            window_failures: window.failures,
+            window_slow_calls: window.slow_calls,
+        }
+    }
+
+    // Adds a finished call to the lifetime totals and the rolling window.
+    fn count(&mut self, now: Instant, sample: Sample) {
+        self.rolling.record(now, sample);
+        if sample.failed {
+            self.total_failures = self.total_failures.saturating_add(1);
+        } else {
+            self.total_successes = self.total_successes.saturating_add(1);
+        }
+        if sample.slow {
+            self.total_slow_calls = self.total_slow_calls.saturating_add(1);
+        }
+    }
+}
+
+// How an admitted call came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    Returned,
+    TimedOut,
+    // Dropped before an outcome was recorded.
+    Cancelled,
+}
+
+// What the breaker decided about a call when it was admitted.
+struct Admission {
+    config: Arc<CircuitBreakerConfig>,
+    period: u64,
+    probe: bool,
+    // Let through by shadow mode although the breaker would have rejected
+    // it; its outcome only counts towards the totals.
+    shadow: bool,
 }
 
 impl CircuitBreaker {
+    /// `timeout` is in seconds and `pause_time` in milliseconds. Values the
+    /// builder rejects keep their original meaning: `max_failures = 0` trips
+    /// on the first failure like `1`, and `timeout = 0` lets the next call
+    /// through right away. Use `try_new` to have them reported instead.
     pub fn new(max_failures: u32, timeout: u64, pause_time: u64) -> Self {
-        let (sender, _receiver) = broadcast::channel(16);
-        Self {
+        let timeout = match timeout {
+            0 => Duration::from_nanos(1),
+            secs => Duration::from_secs(secs),
+        };
+        let config = CircuitBreakerConfig::builder()
+            .max_failures(max_failures.max(1))
+            .timeout(timeout)
+            .pause_time(Duration::from_millis(pause_time))
+            .build()
+            .expect("sanitized arguments always validate");
+        Self::with_config(config)
+    }
+
+    /// Like `new`, but returns a `ConfigError` for a zero `max_failures` or
+    /// `timeout`.
+    pub fn try_new(max_failures: u32, timeout: u64, pause_time: u64) -> Result<Self, ConfigError> {
+        let config = CircuitBreakerConfig::builder()
+            .max_failures(max_failures)
+            .timeout(Duration::from_secs(timeout))
+            .pause_time(Duration::from_millis(pause_time))
+            .build()?;
+        Ok(Self::with_config(config))
+    }
+
+    pub fn with_config(config: CircuitBreakerConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+
+    pub fn with_clock<K: Clock>(config: CircuitBreakerConfig, clock: K) -> Self {
+        Self::with_shared_clock(config, Arc::new(clock))
+    }
+
+    pub(crate) fn with_shared_clock(config: CircuitBreakerConfig, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        let rng = match config.rng_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let events = EventBus::new(config.event_capacity, config.lag_policy);
+        let core = Core {
             state: CircuitBreakerState::Closed,
             consecutive_failures: 0,
+            consecutive_successes: 0,
             total_failures: 0,
             total_successes: 0,
-            max_failures,
-            timeout: Duration::from_secs(timeout),
-            open_timeout: Instant::now(),
-            pause_time: Duration::from_millis(pause_time),
-            consecutive_successes: 0,
-            sender,
+            total_slow_calls: 0,
+            total_timeouts: 0,
+            total_shadow_rejections: 0,
+            total_cancellations: 0,
+            total_fallbacks: 0,
+            open_timeout: now,
+            half_open_calls: 0,
+            shadow_reported: false,
+            period: 0,
+            reopens: 0,
+            closed_since: now,
+            open_duration: config.timeout,
+            rng,
+            window: Window::new(config.sliding_window, now),
+            rolling: Window::new(rolling_window(&config), now),
+            config: Arc::new(config),
+        };
+        Self {
+            inner: Arc::new(Inner {
+                clock,
+                core: Mutex::new(core),
+                events,
+            }),
+        }
+    }
+
+    pub fn config(&self) -> Arc<CircuitBreakerConfig> {
+        Arc::clone(&self.core().config)
+    }
+
+    /// Replaces the configuration of a live breaker. State, counters and
+    /// in-flight calls are kept; calls already admitted finish under the
+    /// configuration they started with. A new open timeout only applies from
+    /// the next time the breaker opens, and `event_capacity` and `lag_policy`
+    /// stay as they were when the breaker was created.
+    pub fn update_config(&self, config: CircuitBreakerConfig) {
+        let now = self.now();
+        let mut core = self.core();
+        if config.sliding_window != core.config.sliding_window {
+            core.window.resize(config.sliding_window, now);
         }
+        if config.rolling_window != core.config.rolling_window {
+            core.rolling.resize(rolling_window(&config), now);
+        }
+        if config.rng_seed != core.config.rng_seed {
+            if let Some(seed) = config.rng_seed {
+                core.rng = StdRng::seed_from_u64(seed);
+            }
+        }
+        core.config = Arc::new(config);
+
+        let state = core.state;
+        self.inner.events.publish(CircuitBreakerEvent {
+            from: state,
+            to: state,
+            at: now,
+            timestamp: self.inner.clock.system_time(),
+            reason: TransitionReason::ConfigChanged,
+            metrics: core.metrics(now),
+        });
+    }
+
+    pub fn state(&self) -> CircuitBreakerState {
+        self.core().state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.core().consecutive_failures
+    }
+
+    pub fn consecutive_successes(&self) -> u32 {
+        self.core().consecutive_successes
+    }
+
+    pub fn total_failures(&self) -> u64 {
+        self.core().total_failures
+    }
+
+    pub fn total_successes(&self) -> u64 {
+        self.core().total_successes
+    }
+
+    pub fn metrics(&self) -> CircuitBreakerMetrics {
+        self.core().metrics(self.now())
+    }
+
+    pub fn open_timeout(&self) -> Instant {
+        self.core().open_timeout
     }
 
-    pub async fn execute<F, Fut>(&mut self, mut func: F) -> Result<String, String>
+    pub fn max_failures(&self) -> Option<u32> {
+        self.config().max_failures
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.config().timeout
+    }
+
+    pub fn pause_time(&self) -> Duration {
+        self.config().pause_time
+    }
+
+    pub async fn execute<F, Fut, T, E>(&self, func: F) -> Result<T, CircuitBreakerError<E>>
     where
         F: FnMut() -> Fut,
-        Fut: std::future::Future<Output = Result<String, String>>,
+        Fut: std::future::Future<Output = Result<T, E>>,
     {
-        match self.state {
-            CircuitBreakerState::Open => {
-                if Instant::now() > self.open_timeout {
-                    self.state = CircuitBreakerState::HalfOpen;
-                    self.sender.send("halfOpen".to_string()).unwrap();
-                } else {
-                    return Err("Circuit breaker is open".to_string());
+        self.execute_with_classifier(func, CallOutcome::from_result)
+            .await
+    }
+
+    /// Like `execute`, but `classify` decides how each result is accounted
+    /// for instead of treating every `Ok` as a success and every `Err` as a
+    /// failure.
+    pub async fn execute_with_classifier<F, Fut, T, E, C>(
+        &self,
+        mut func: F,
+        classify: C,
+    ) -> Result<T, CircuitBreakerError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, E>>,
+        C: FnOnce(&Result<T, E>) -> CallOutcome,
+    {
+        // If this future is dropped mid-call, the permit records the call as
+        // cancelled and gives back its probe slot.
+        let permit = self
+            .try_acquire()
+            .ok_or(CircuitBreakerError::Open)?
+            .cancel_on_drop();
+        let probe = permit.is_probe();
+        let config = permit.config().unwrap_or_else(|| self.config());
+
+        let call = async {
+            match config.panic_policy {
+                PanicPolicy::Propagate => Ok(func().await),
+                PanicPolicy::Resume | PanicPolicy::Return => panic::catch_unwind(&mut func).await,
+            }
+        };
+        let result = match config.call_timeout {
+            Some(limit) => tokio::select! {
+                result = call => Some(result),
+                _ = self.inner.clock.sleep(limit) => None,
+            },
+            None => Some(call.await),
+        };
+        let elapsed = permit.elapsed();
+        match &result {
+            Some(Ok(result)) => permit.record(classify(result), elapsed),
+            Some(Err(_)) => permit.record(CallOutcome::Failure, elapsed),
+            None => permit.record_timeout(elapsed),
+        }
+
+        if probe {
+            self.delay(config.pause_time).await;
+        }
+        match result {
+            Some(Ok(result)) => result.map_err(CircuitBreakerError::Inner),
+            Some(Err(payload)) => match config.panic_policy {
+                PanicPolicy::Resume => payload.resume(),
+                _ => Err(CircuitBreakerError::Panicked(payload)),
+            },
+            None => Err(CircuitBreakerError::Timeout),
+        }
+    }
+
+    /// Admits a single call without running it, or returns `None` if the
+    /// breaker rejects it. The outcome is reported through the `Permit`.
+    pub fn try_acquire(&self) -> Option<Permit> {
+        self.admit()
+            .map(|admission| Permit::new(self.clone(), admission))
+    }
+
+    // Records how a call admitted by `admit` ended.
+    fn finish(
+        &self,
+        admission: &Admission,
+        outcome: CallOutcome,
+        elapsed: Duration,
+        ending: Ending,
+    ) {
+        let slow = admission
+            .config
+            .slow_call_duration_threshold
+            .is_some_and(|threshold| elapsed >= threshold);
+        let mut core = self.core();
+        if core.state != CircuitBreakerState::Disabled {
+            match ending {
+                Ending::Returned => {}
+                Ending::TimedOut => {
+                    core.total_timeouts = core.total_timeouts.saturating_add(1);
+                }
+                Ending::Cancelled => {
+                    core.total_cancellations = core.total_cancellations.saturating_add(1);
                 }
             }
-            CircuitBreakerState::HalfOpen => {
-                let result = func().await;
-                self.delay(self.pause_time).await;
-                return result;
+        }
+        match outcome {
+            CallOutcome::Success => self.complete_locked(
+                &mut core,
+                admission,
+                Sample {
+                    failed: false,
+                    slow,
+                },
+            ),
+            CallOutcome::Failure => {
+                self.complete_locked(&mut core, admission, Sample { failed: true, slow })
+            }
+            CallOutcome::Ignored => self.release_locked(&mut core, admission),
+        }
+    }
+
+    // Records the outcome of a call admitted by `admit`.
+    fn complete_locked(&self, core: &mut Core, admission: &Admission, sample: Sample) {
+        if core.period != admission.period || admission.shadow {
+            // The breaker moved on while the call was in flight, or never
+            // meant to run it; keep the totals accurate but don't let the
+            // outcome drive the state.
+            core.count(self.now(), sample);
+        } else if sample.failed {
+            self.on_failure_locked(core, sample.slow);
+        } else {
+            self.on_success_locked(core, sample.slow);
+        }
+    }
+
+    // Gives back an admitted call that ended without an outcome worth
+    // recording, so its half-open probe slot can be reused.
+    fn release_locked(&self, core: &mut Core, admission: &Admission) {
+        if admission.probe && core.period == admission.period {
+            core.half_open_calls = core.half_open_calls.saturating_sub(1);
+        }
+    }
+
+    fn admit(&self) -> Option<Admission> {
+        let mut core = self.core();
+        if core.state == CircuitBreakerState::Open && self.now() > core.open_timeout {
+            self.transition_locked(
+                &mut core,
+                CircuitBreakerState::HalfOpen,
+                TransitionReason::OpenTimeoutElapsed,
+            );
+        }
+
+        let probe = core.state == CircuitBreakerState::HalfOpen
+            && core.half_open_calls < core.config.half_open_max_calls;
+        // Shadow mode only overrides the breaker's own decisions; an operator
+        // forcing it open always wins.
+        let rejected = match core.state {
+            CircuitBreakerState::ForcedOpen => return None,
+            CircuitBreakerState::Open => true,
+            CircuitBreakerState::HalfOpen => !probe,
+            _ => false,
+        };
+        if rejected {
+            if !core.config.shadow {
+                return None;
+            }
+            self.shadow_reject_locked(&mut core);
+        }
+        if probe {
+            core.half_open_calls += 1;
+        }
+        Some(Admission {
+            config: Arc::clone(&core.config),
+            period: core.period,
+            probe,
+            shadow: rejected,
+        })
+    }
+
+    // Counts a call shadow mode let through. Only the first one in each
+    // period is published, so a busy breaker that stays open cannot crowd
+    // real transitions out of the event channel.
+    fn shadow_reject_locked(&self, core: &mut Core) {
+        core.total_shadow_rejections = core.total_shadow_rejections.saturating_add(1);
+        if core.shadow_reported {
+            return;
+        }
+        core.shadow_reported = true;
+        let now = self.now();
+        self.inner.events.publish(CircuitBreakerEvent {
+            from: core.state,
+            to: core.state,
+            at: now,
+            timestamp: self.inner.clock.system_time(),
+            reason: TransitionReason::ShadowRejected,
+            metrics: core.metrics(now),
+        });
+    }
+
+    pub fn handle_failure(&self) {
+        let mut core = self.core();
+        self.on_failure_locked(&mut core, false);
+    }
+
+    pub fn handle_success(&self) {
+        let mut core = self.core();
+        self.on_success_locked(&mut core, false);
+    }
+
+    /// `trip()` and `reset()` have no effect while an override is active;
+    /// only `release()` ends one.
+    pub fn trip(&self) {
+        let mut core = self.core();
+        if !core.state.is_override() {
+            self.trip_locked(&mut core, TransitionReason::Manual);
+        }
+    }
+
+    pub fn reset(&self) {
+        let mut core = self.core();
+        if !core.state.is_override() {
+            self.reset_locked(&mut core, TransitionReason::Manual);
+        }
+    }
+
+    pub fn force_open(&self) {
+        self.set_override(CircuitBreakerState::ForcedOpen);
+    }
+
+    pub fn force_closed(&self) {
+        self.set_override(CircuitBreakerState::ForcedClosed);
+    }
+
+    pub fn disable(&self) {
+        self.set_override(CircuitBreakerState::Disabled);
+    }
+
+    pub fn metrics_only(&self) {
+        self.set_override(CircuitBreakerState::MetricsOnly);
+    }
+
+    /// Ends an override; the breaker starts over closed.
+    pub fn release(&self) {
+        let mut core = self.core();
+        if core.state.is_override() {
+            self.reset_locked(&mut core, TransitionReason::OverrideReleased);
+        }
+    }
+
+    fn set_override(&self, state: CircuitBreakerState) {
+        let mut core = self.core();
+        if core.state != state {
+            self.transition_locked(&mut core, state, TransitionReason::Override);
+        }
+    }
+
+    fn on_failure_locked(&self, core: &mut Core, slow: bool) {
+        if core.state == CircuitBreakerState::Disabled {
+            return;
+        }
+        let now = self.now();
+        let sample = Sample { failed: true, slow };
+        core.count(now, sample);
+        match core.state {
+            CircuitBreakerState::Closed => {
+                core.consecutive_failures += 1;
+                core.window.record(now, sample);
+                if let Some(reason) = self.trip_reason(core, now) {
+                    self.trip_locked(core, reason);
+                }
+            }
+            // A single failed probe is enough to know the dependency has not
+            // recovered yet.
+            CircuitBreakerState::HalfOpen => self.trip_locked(core, TransitionReason::ProbeFailed),
+            CircuitBreakerState::MetricsOnly => {
+                core.consecutive_failures += 1;
+                core.window.record(now, sample);
             }
             _ => {}
         }
+    }
 
-        let result = func().await;
-        match result {
-            Ok(res) => {
-                self.handle_success();
-                Ok(res)
+    fn on_success_locked(&self, core: &mut Core, slow: bool) {
+        if core.state == CircuitBreakerState::Disabled {
+            return;
+        }
+        let now = self.now();
+        let sample = Sample {
+            failed: false,
+            slow,
+        };
+        core.count(now, sample);
+        match core.state {
+            CircuitBreakerState::Closed => {
+                core.consecutive_failures = 0;
+                core.window.record(now, sample);
+                // Reaching `minimum_calls` on a success can still put the
+                // window over a rate threshold.
+                if let Some(reason) = self.trip_reason(core, now) {
+                    self.trip_locked(core, reason);
+                }
+            }
+            CircuitBreakerState::HalfOpen => {
+                core.consecutive_successes += 1;
+                if core.consecutive_successes >= core.config.half_open_success_threshold {
+                    self.reset_locked(core, TransitionReason::ProbesSucceeded);
+                }
             }
-            Err(err) => {
-                self.handle_failure();
-                Err(err)
+            CircuitBreakerState::MetricsOnly => {
+                core.consecutive_failures = 0;
+                core.window.record(now, sample);
             }
+            _ => {}
         }
     }
 
-    pub fn handle_failure(&mut self) {
-        self.consecutive_failures += 1;
-        self.total_failures += 1;
-        if self.consecutive_failures >= self.max_failures {
-            self.trip();
+    // Checks the closed-state tripping rules that are enabled, in order.
+    fn trip_reason(&self, core: &Core, now: Instant) -> Option<TransitionReason> {
+        let config = &core.config;
+        if let Some(max_failures) = config.max_failures {
+            if core.consecutive_failures >= max_failures {
+                return Some(TransitionReason::ConsecutiveFailures);
+            }
+        }
+        let window = core.window.stats(now);
+        if window.calls == 0 || window.calls < config.minimum_calls {
+            return None;
+        }
+        let rate = |count: u32| f64::from(count) * 100.0 / f64::from(window.calls);
+        if let Some(threshold) = config.failure_rate_threshold {
+            if rate(window.failures) >= threshold {
+                return Some(TransitionReason::FailureRate);
+            }
         }
+        if let Some(threshold) = config.slow_call_rate_threshold {
+            if rate(window.slow_calls) >= threshold {
+                return Some(TransitionReason::SlowCallRate);
+            }
+        }
+        None
     }
 
-    pub fn handle_success(&mut self) {
-        self.reset();
-        self.total_successes += 1;
+    fn trip_locked(&self, core: &mut Core, reason: TransitionReason) {
+        self.transition_locked(core, CircuitBreakerState::Open, reason);
     }
 
-    pub fn trip(&mut self) {
-        self.state = CircuitBreakerState::Open;
-        self.consecutive_failures = 0;
-        self.consecutive_successes = 0;
-        self.open_timeout = Instant::now() + self.timeout;
-        self.sender.send("open".to_string()).unwrap();
+    fn reset_locked(&self, core: &mut Core, reason: TransitionReason) {
+        self.transition_locked(core, CircuitBreakerState::Closed, reason);
     }
 
-    pub fn reset(&mut self) {
-        self.state = CircuitBreakerState::Closed;
-        self.consecutive_failures = 0;
-        self.consecutive_successes = 0;
+    fn transition_locked(
+        &self,
+        core: &mut Core,
+        to: CircuitBreakerState,
+        reason: TransitionReason,
+    ) {
+        let now = self.now();
+        let metrics = core.metrics(now);
+        let from = core.state;
 
-        // Handle potential error when sending "close"
-        if let Err(err) = self.sender.send("close".to_string()) {
-            eprintln!("Error sending 'close' message: {:?}", err);
-            // Handle the error as needed, maybe retry or log it
+        core.state = to;
+        core.period += 1;
+        core.consecutive_failures = 0;
+        core.consecutive_successes = 0;
+        core.half_open_calls = 0;
+        core.shadow_reported = false;
+        core.window.clear();
+        match to {
+            CircuitBreakerState::Open => {
+                core.open_timeout = now + self.open_duration_locked(core, from, now);
+            }
+            CircuitBreakerState::Closed => core.closed_since = now,
+            _ => {}
         }
+
+        self.inner.events.publish(CircuitBreakerEvent {
+            from,
+            to,
+            at: now,
+            timestamp: self.inner.clock.system_time(),
+            reason,
+            metrics,
+        });
+    }
+
+    fn open_duration_locked(
+        &self,
+        core: &mut Core,
+        from: CircuitBreakerState,
+        now: Instant,
+    ) -> Duration {
+        let config = Arc::clone(&core.config);
+        let (duration, cap) = match config.backoff {
+            Some(backoff) => {
+                if from == CircuitBreakerState::HalfOpen {
+                    core.reopens = core.reopens.saturating_add(1);
+                } else if now.saturating_duration_since(core.closed_since) >= backoff.reset_after {
+                    core.reopens = 0;
+                    // Decorrelated jitter grows from the previous period,
+                    // so it has to start over too.
+                    core.open_duration = config.timeout;
+                }
+                // Jitter never goes past the backed-off period.
+                let duration = backoff.open_duration(config.timeout, core.reopens);
+                (duration, duration)
+            }
+            None => (config.timeout, config.timeout.saturating_mul(3)),
+        };
+        let previous = core.open_duration;
+        core.open_duration =
+            config
+                .jitter
+                .apply(&mut core.rng, duration, config.timeout, previous, cap);
+        core.open_duration
+    }
+
+    fn core(&self) -> MutexGuard<'_, Core> {
+        // A panic while the lock was held cannot leave the counters in a
+        // state worse than stale, so keep serving instead of propagating it.
+        self.inner
+            .core
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
     }
 
     async fn delay(&self, duration: Duration) {
-        sleep(duration).await;
+        self.inner.clock.sleep(duration).await;
+    }
+
+    fn now(&self) -> Instant {
+        self.inner.clock.now()
+    }
+
+    pub fn subscribe(&self) -> EventStream {
+        self.inner.events.subscribe()
+    }
+
+    pub fn lagged_events(&self) -> u64 {
+        self.inner.events.lagged()
     }
 
-    pub fn set_on_open<F>(&self, mut callback: F)
+    pub fn set_on_open<F>(&self, callback: F)
     where
         F: FnMut() + Send + 'static,
     {
-        let mut receiver = self.sender.subscribe();
-        tokio::spawn(async move {
-            while let Ok(message) = receiver.recv().await {
-                if message == "open" {
-                    callback();
-                }
-            }
-        });
+        self.inner
+            .events
+            .listen(CircuitBreakerState::Open, callback);
     }
 
-    pub fn set_on_close<F>(&self, mut callback: F)
+    pub fn set_on_close<F>(&self, callback: F)
     where
         F: FnMut() + Send + 'static,
     {
-        let mut receiver = self.sender.subscribe();
-        tokio::spawn(async move {
-            while let Ok(message) = receiver.recv().await {
-                if message == "close" {
-                    callback();
-                }
-            }
-        });
+        self.inner
+            .events
+            .listen(CircuitBreakerState::Closed, callback);
     }
 
-    pub fn set_on_half_open<F>(&self, mut callback: F)
+    pub fn set_on_half_open<F>(&self, callback: F)
     where
         F: FnMut() + Send + 'static,
     {
-        let mut receiver = self.sender.subscribe();
-        tokio::spawn(async move {
-            while let Ok(message) = receiver.recv().await {
-                if message == "halfOpen" {
-                    callback();
-                }
+        self.inner
+            .events
+            .listen(CircuitBreakerState::HalfOpen, callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::{pending, ready};
+
+    fn breaker(config: CircuitBreakerConfigBuilder) -> (CircuitBreaker, MockClock) {
+        let clock = MockClock::new();
+        let breaker = CircuitBreaker::with_clock(config.build().unwrap(), clock.clone());
+        (breaker, clock)
+    }
+
+    fn fail(breaker: &CircuitBreaker) {
+        breaker
+            .try_acquire()
+            .unwrap()
+            .record_failure((), Duration::ZERO);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn clones_share_one_breaker_across_tasks() {
+        let breaker = CircuitBreaker::with_config(
+            CircuitBreakerConfig::builder()
+                .max_failures(u32::MAX)
+                .build()
+                .unwrap(),
+        );
+        let tasks: Vec<_> = (0..32)
+            .map(|task| {
+                let breaker = breaker.clone();
+                tokio::spawn(async move {
+                    for call in 0..100 {
+                        let failed = (task + call) % 4 == 0;
+                        let _ = breaker
+                            .execute(|| async move {
+                                tokio::task::yield_now().await;
+                                if failed {
+                                    Err(())
+                                } else {
+                                    Ok(())
+                                }
+                            })
+                            .await;
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.total_failures, 800);
+        assert_eq!(metrics.total_successes, 2400);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+    }
+
+    #[test]
+    fn new_accepts_zero_arguments_and_try_new_rejects_them() {
+        let breaker = CircuitBreaker::new(0, 0, 0);
+        assert_eq!(breaker.max_failures(), Some(1));
+        assert_eq!(breaker.timeout(), Duration::from_nanos(1));
+
+        assert!(matches!(
+            CircuitBreaker::try_new(0, 30, 0),
+            Err(ConfigError::Zero {
+                field: "max_failures"
+            })
+        ));
+        assert!(matches!(
+            CircuitBreaker::try_new(3, 0, 0),
+            Err(ConfigError::Zero { field: "timeout" })
+        ));
+    }
+
+    #[test]
+    fn half_open_admits_up_to_max_calls_probes() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10))
+                .half_open_max_calls(2)
+                .half_open_success_threshold(2),
+        );
+        fail(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        assert!(breaker.try_acquire().is_none());
+
+        clock.advance(Duration::from_secs(11));
+        let first = breaker.try_acquire().unwrap();
+        let second = breaker.try_acquire().unwrap();
+        assert!(first.is_probe() && second.is_probe());
+        assert_eq!(breaker.state(), CircuitBreakerState::HalfOpen);
+        assert!(breaker.try_acquire().is_none());
+
+        first.record_success(Duration::ZERO);
+        assert_eq!(breaker.state(), CircuitBreakerState::HalfOpen);
+        // Dropped unrecorded, the probe is ignored and its slot reused.
+        drop(second);
+        let third = breaker.try_acquire().unwrap();
+        assert!(third.is_probe());
+        third.record_success(Duration::ZERO);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+    }
+
+    #[test]
+    fn failed_probe_reopens() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10)),
+        );
+        fail(&breaker);
+        clock.advance(Duration::from_secs(11));
+        fail(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        assert_eq!(
+            breaker.open_timeout() - clock.now(),
+            Duration::from_secs(10)
+        );
+    }
+
+    fn succeed(breaker: &CircuitBreaker) {
+        breaker
+            .try_acquire()
+            .unwrap()
+            .record_success(Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn classifier_can_fail_ok_results() {
+        let (breaker, _) = breaker(CircuitBreakerConfig::builder().max_failures(1));
+        let result = breaker
+            .execute_with_classifier(
+                || async { Ok::<u16, ()>(503) },
+                |result| match result {
+                    Ok(status) if *status >= 500 => CallOutcome::Failure,
+                    result => CallOutcome::from_result(result),
+                },
+            )
+            .await;
+
+        assert_eq!(result, Ok(503));
+        assert_eq!(breaker.metrics().total_failures, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn ignored_probe_leaves_stats_alone_and_frees_its_slot() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10)),
+        );
+        fail(&breaker);
+        clock.advance(Duration::from_secs(11));
+        let before = breaker.metrics();
+
+        let result = breaker
+            .execute_with_classifier(
+                || async { Err::<(), _>("not found") },
+                |_| CallOutcome::Ignored,
+            )
+            .await;
+
+        assert_eq!(result, Err(CircuitBreakerError::Inner("not found")));
+        assert_eq!(breaker.metrics(), before);
+        assert_eq!(breaker.state(), CircuitBreakerState::HalfOpen);
+        assert!(breaker.try_acquire().unwrap().is_probe());
+    }
+
+    #[test]
+    fn failure_rate_trips_over_count_window() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(None)
+                .failure_rate_threshold(50.0)
+                .sliding_window(SlidingWindow::Count(4))
+                .minimum_calls(4),
+        );
+        fail(&breaker);
+        fail(&breaker);
+        succeed(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+        succeed(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    #[test]
+    fn failure_rate_forgets_calls_outside_time_window() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(None)
+                .failure_rate_threshold(50.0)
+                .sliding_window(SlidingWindow::Time {
+                    window: Duration::from_secs(10),
+                    buckets: 5,
+                })
+                .minimum_calls(2),
+        );
+        fail(&breaker);
+        clock.advance(Duration::from_secs(11));
+        assert_eq!(breaker.metrics().window_calls, 0);
+        succeed(&breaker);
+        succeed(&breaker);
+        fail(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+        fail(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn slow_successes_trip_on_slow_call_rate() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(None)
+                .slow_call_duration_threshold(Duration::from_secs(1))
+                .slow_call_rate_threshold(50.0)
+                .sliding_window(SlidingWindow::Count(4))
+                .minimum_calls(4),
+        );
+        let mut events = breaker.subscribe();
+        let slow = Duration::from_secs(2);
+        fail(&breaker);
+        breaker.try_acquire().unwrap().record_success(slow);
+        breaker.try_acquire().unwrap().record_success(slow);
+
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.total_slow_calls, 2);
+        assert_eq!(metrics.total_failures, 1);
+        assert_eq!(metrics.total_successes, 2);
+        assert_eq!(metrics.window_slow_calls, 2);
+        assert_eq!(metrics.window_failures, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+
+        succeed(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reason, TransitionReason::SlowCallRate);
+        assert_eq!(event.metrics.window_slow_calls, 2);
+    }
+
+    #[test]
+    fn rolling_counts_cover_rolling_window_only() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(10)
+                .rolling_window(Duration::from_secs(60)),
+        );
+        fail(&breaker);
+        succeed(&breaker);
+        clock.advance(Duration::from_secs(30));
+        succeed(&breaker);
+
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.rolling_failures, 1);
+        assert_eq!(metrics.rolling_successes, 2);
+
+        clock.advance(Duration::from_secs(40));
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.rolling_failures, 0);
+        assert_eq!(metrics.rolling_successes, 1);
+        assert_eq!(metrics.total_failures, 1);
+        assert_eq!(metrics.total_successes, 2);
+    }
+
+    #[tokio::test]
+    async fn call_timeout_runs_on_the_breaker_clock() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .call_timeout(Duration::from_secs(1)),
+        );
+        let call = tokio::spawn({
+            let breaker = breaker.clone();
+            let clock = clock.clone();
+            async move {
+                breaker
+                    .execute(|| async {
+                        clock.sleep(Duration::from_secs(5)).await;
+                        Ok::<_, ()>(())
+                    })
+                    .await
             }
         });
+        settle().await;
+        clock.advance(Duration::from_secs(2));
+
+        assert!(call.await.unwrap().unwrap_err().is_timeout());
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.total_timeouts, 1);
+        assert_eq!(metrics.total_failures, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    // Lets spawned tasks run up to their next await.
+    async fn settle() {
+        for _ in 0..5 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[test]
+    fn backoff_lengthens_open_period_on_each_reopen() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10))
+                .backoff(Backoff {
+                    multiplier: 2.0,
+                    max_timeout: Duration::from_secs(30),
+                    reset_after: Duration::from_secs(60),
+                }),
+        );
+        fail(&breaker);
+        let mut open_periods = Vec::new();
+        for _ in 0..3 {
+            let open_period = breaker.open_timeout() - clock.now();
+            open_periods.push(open_period);
+            clock.advance(open_period + Duration::from_secs(1));
+            fail(&breaker);
+        }
+        assert_eq!(open_periods, [10, 20, 30].map(Duration::from_secs).to_vec());
+    }
+
+    #[test]
+    fn decorrelated_jitter_follows_backoff_and_its_reset() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10))
+                .backoff(Backoff {
+                    multiplier: 2.0,
+                    max_timeout: Duration::from_secs(600),
+                    reset_after: Duration::from_secs(60),
+                })
+                .jitter(Jitter::Decorrelated)
+                .rng_seed(1),
+        );
+        let open_period = || breaker.open_timeout() - clock.now();
+        fail(&breaker);
+        assert_eq!(open_period(), Duration::from_secs(10));
+        for reopens in 1..6 {
+            clock.advance(open_period() + Duration::from_secs(1));
+            fail(&breaker);
+            let backed_off = Duration::from_secs(10 * 2u64.pow(reopens));
+            assert!(open_period() >= Duration::from_secs(10));
+            assert!(open_period() <= backed_off);
+        }
+
+        clock.advance(open_period() + Duration::from_secs(1));
+        succeed(&breaker);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+        clock.advance(Duration::from_secs(3600));
+        fail(&breaker);
+        assert_eq!(open_period(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn seeded_jitter_is_reproducible() {
+        let config = CircuitBreakerConfig::builder()
+            .max_failures(1)
+            .timeout(Duration::from_secs(10))
+            .jitter(Jitter::Full)
+            .rng_seed(7)
+            .build()
+            .unwrap();
+        let clock = MockClock::new();
+        let first = CircuitBreaker::with_clock(config.clone(), clock.clone());
+        let second = CircuitBreaker::with_clock(config, clock.clone());
+        fail(&first);
+        fail(&second);
+        assert_eq!(first.open_timeout(), second.open_timeout());
+        assert!(first.open_timeout() <= clock.now() + Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn update_config_keeps_state_and_admitted_configs() {
+        let window = SlidingWindow::Time {
+            window: Duration::from_secs(60),
+            buckets: 6,
+        };
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(3)
+                .sliding_window(window)
+                .dropped_permit_outcome(CallOutcome::Failure),
+        );
+        let mut events = breaker.subscribe();
+        fail(&breaker);
+        let in_flight = breaker.try_acquire().unwrap();
+
+        breaker.update_config(
+            CircuitBreakerConfig::builder()
+                .max_failures(2)
+                .sliding_window(SlidingWindow::Time {
+                    window: Duration::from_secs(120),
+                    buckets: 4,
+                })
+                .build()
+                .unwrap(),
+        );
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reason, TransitionReason::ConfigChanged);
+        assert_eq!(breaker.max_failures(), Some(2));
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.consecutive_failures, 1);
+        assert_eq!(metrics.window_failures, 1);
+
+        // Admitted before the change, the dropped permit still counts as a
+        // failure, which reaches the new `max_failures`.
+        drop(in_flight);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn forced_open_rejects_until_released() {
+        let (breaker, _) = breaker(CircuitBreakerConfig::builder().max_failures(1));
+        let mut events = breaker.subscribe();
+        breaker.force_open();
+        assert!(breaker.try_acquire().is_none());
+        let result = breaker.execute(|| async { Ok::<_, ()>(()) }).await;
+        assert!(matches!(result, Err(CircuitBreakerError::Open)));
+
+        breaker.release();
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+        assert!(breaker.try_acquire().is_some());
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(
+            (event.from, event.to, event.reason),
+            (
+                CircuitBreakerState::Closed,
+                CircuitBreakerState::ForcedOpen,
+                TransitionReason::Override
+            )
+        );
+        let event = events.recv().await.unwrap();
+        assert_eq!(
+            (event.from, event.to, event.reason),
+            (
+                CircuitBreakerState::ForcedOpen,
+                CircuitBreakerState::Closed,
+                TransitionReason::OverrideReleased
+            )
+        );
+    }
+
+    #[test]
+    fn overrides_outlast_trip_reset_and_tripping_rules() {
+        for state in [
+            CircuitBreakerState::ForcedOpen,
+            CircuitBreakerState::ForcedClosed,
+            CircuitBreakerState::Disabled,
+            CircuitBreakerState::MetricsOnly,
+        ] {
+            let (breaker, _) = breaker(CircuitBreakerConfig::builder().max_failures(1));
+            match state {
+                CircuitBreakerState::ForcedOpen => breaker.force_open(),
+                CircuitBreakerState::ForcedClosed => breaker.force_closed(),
+                CircuitBreakerState::Disabled => breaker.disable(),
+                _ => breaker.metrics_only(),
+            }
+            breaker.trip();
+            assert_eq!(breaker.state(), state);
+            breaker.reset();
+            assert_eq!(breaker.state(), state);
+            breaker.handle_failure();
+            breaker.handle_failure();
+            assert_eq!(breaker.state(), state);
+
+            breaker.release();
+            assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+            breaker.handle_failure();
+            assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        }
+    }
+
+    #[test]
+    fn metrics_only_records_calls_without_tripping() {
+        let (breaker, _) = breaker(CircuitBreakerConfig::builder().max_failures(1));
+        breaker.metrics_only();
+        fail(&breaker);
+        fail(&breaker);
+        succeed(&breaker);
+
+        assert_eq!(breaker.state(), CircuitBreakerState::MetricsOnly);
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.window_calls, 3);
+        assert_eq!(metrics.window_failures, 2);
+        assert_eq!(metrics.total_failures, 2);
+        assert_eq!(metrics.total_successes, 1);
+    }
+
+    #[test]
+    fn disabled_records_nothing() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .dropped_permit_outcome(CallOutcome::Failure),
+        );
+        breaker.disable();
+        fail(&breaker);
+        succeed(&breaker);
+        drop(breaker.try_acquire().unwrap());
+
+        assert_eq!(breaker.state(), CircuitBreakerState::Disabled);
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.window_calls, 0);
+        assert_eq!(metrics.total_failures, 0);
+        assert_eq!(metrics.total_successes, 0);
+        assert_eq!(metrics.total_cancellations, 0);
+        assert_eq!(metrics.rolling_failures + metrics.rolling_successes, 0);
+    }
+
+    #[tokio::test]
+    async fn shadow_mode_lets_rejected_calls_through() {
+        let (breaker, _) = breaker(CircuitBreakerConfig::builder().max_failures(1).shadow(true));
+        let mut events = breaker.subscribe();
+        breaker.trip();
+        succeed(&breaker);
+        fail(&breaker);
+        succeed(&breaker);
+
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.total_shadow_rejections, 3);
+        assert_eq!(metrics.total_successes, 2);
+        assert_eq!(metrics.total_failures, 1);
+
+        // Only the first rejection of each period is published.
+        breaker.reset();
+        breaker.trip();
+        fail(&breaker);
+        let reasons = [
+            TransitionReason::Manual,
+            TransitionReason::ShadowRejected,
+            TransitionReason::Manual,
+            TransitionReason::Manual,
+            TransitionReason::ShadowRejected,
+        ];
+        for reason in reasons {
+            assert_eq!(events.recv().await.unwrap().reason, reason);
+        }
+
+        breaker.force_open();
+        assert!(breaker.try_acquire().is_none());
+        assert_eq!(breaker.metrics().total_shadow_rejections, 4);
+    }
+
+    #[test]
+    fn dropped_permit_counts_as_configured_outcome() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .dropped_permit_outcome(CallOutcome::Failure),
+        );
+        drop(breaker.try_acquire().unwrap());
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+        assert_eq!(breaker.metrics().total_cancellations, 1);
+    }
+
+    #[tokio::test]
+    async fn cancelled_probe_gives_back_its_slot() {
+        let (breaker, clock) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .timeout(Duration::from_secs(10)),
+        );
+        fail(&breaker);
+        clock.advance(Duration::from_secs(11));
+
+        let mut call = Box::pin(breaker.execute(pending::<Result<(), ()>>));
+        tokio::select! {
+            biased;
+            _ = &mut call => unreachable!("the call never finishes"),
+            _ = ready(()) => {}
+        }
+        assert!(breaker.try_acquire().is_none());
+        drop(call);
+
+        assert_eq!(breaker.metrics().total_cancellations, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::HalfOpen);
+        assert!(breaker.try_acquire().unwrap().is_probe());
+    }
+
+    fn panicking(
+        breaker: &CircuitBreaker,
+    ) -> tokio::task::JoinHandle<Result<(), CircuitBreakerError<()>>> {
+        let breaker = breaker.clone();
+        tokio::spawn(async move { breaker.execute(|| async { panic!("boom") }).await })
+    }
+
+    #[tokio::test]
+    async fn panic_policy_return_reports_failure() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(2)
+                .panic_policy(PanicPolicy::Return),
+        );
+        match panicking(&breaker).await.unwrap() {
+            Err(CircuitBreakerError::Panicked(payload)) => {
+                assert_eq!(payload.message(), Some("boom"))
+            }
+            other => panic!("expected a caught panic, got {:?}", other),
+        }
+        assert_eq!(breaker.metrics().total_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn panic_policy_resume_records_failure_then_unwinds() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .panic_policy(PanicPolicy::Resume),
+        );
+        assert!(panicking(&breaker).await.unwrap_err().is_panic());
+        assert_eq!(breaker.metrics().total_failures, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::Open);
+    }
+
+    #[tokio::test]
+    async fn panic_policy_propagate_counts_cancellation() {
+        let (breaker, _) = breaker(
+            CircuitBreakerConfig::builder()
+                .max_failures(1)
+                .panic_policy(PanicPolicy::Propagate),
+        );
+        assert!(panicking(&breaker).await.unwrap_err().is_panic());
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.total_failures, 0);
+        assert_eq!(metrics.total_cancellations, 1);
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
     }
 }
@@ -0,0 +1,121 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+// Values by key in least recently used order, shared by the per-key breakers
+// and the stale cache. A key is used when it is inserted or touched.
+pub(crate) struct Lru<K, V> {
+    entries: HashMap<K, Slot<V>>,
+    // Keys by the tick of their last use, least recently used first.
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+struct Slot<V> {
+    value: V,
+    used_at: Instant,
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    // The value for `key` and when it was last used, without using it.
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<(&V, Instant)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.entries.get(key)?;
+        Some((&slot.value, slot.used_at))
+    }
+
+    // Marks `key` as used at `now` and returns its value.
+    pub(crate) fn touch<Q>(&mut self, key: &Q, now: Instant) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.tick += 1;
+        let slot = self.entries.get_mut(key)?;
+        if let Some(key) = self.order.remove(&slot.tick) {
+            self.order.insert(self.tick, key);
+        }
+        slot.used_at = now;
+        slot.tick = self.tick;
+        Some(&mut slot.value)
+    }
+
+    // Inserts `value` as used at `now`, replacing any value for `key`. With
+    // a `capacity`, the least recently used keys make room for a new one.
+    pub(crate) fn insert(&mut self, key: K, value: V, now: Instant, capacity: Option<usize>) {
+        self.remove(&key);
+        if let Some(capacity) = capacity {
+            while self.entries.len() >= capacity && self.remove_oldest() {}
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            Slot {
+                value,
+                used_at: now,
+                tick: self.tick,
+            },
+        );
+    }
+
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.entries.remove(key)?;
+        self.order.remove(&slot.tick);
+        Some(slot.value)
+    }
+
+    // Removes every key not used within `age` of `now`.
+    pub(crate) fn remove_unused(&mut self, now: Instant, age: Duration) {
+        while let Some((_, key)) = self.order.first_key_value() {
+            match self.entries.get(key) {
+                Some(slot) if now.saturating_duration_since(slot.used_at) < age => break,
+                _ => {
+                    self.remove_oldest();
+                }
+            }
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Every key and value, least recently used first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.order
+            .values()
+            .filter_map(|key| Some((key, &self.entries.get(key)?.value)))
+    }
+
+    fn remove_oldest(&mut self) -> bool {
+        match self.order.pop_first() {
+            Some((_, key)) => {
+                self.entries.remove(&key);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+/// Point-in-time copy of a breaker's counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CircuitBreakerMetrics {
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    /// Failures recorded since the breaker was created.
+    pub total_failures: u64,
+    /// Successes recorded since the breaker was created.
+    pub total_successes: u64,
+    /// Slow calls, successful or not, recorded since the breaker was created.
+    pub total_slow_calls: u64,
+    /// Calls cut off by `call_timeout` since the breaker was created.
+    pub total_timeouts: u64,
+    /// Calls a shadow-mode breaker would have rejected but let through.
+    pub total_shadow_rejections: u64,
+    /// Calls abandoned without an outcome since the breaker was created:
+    /// `execute` futures dropped mid-call and permits dropped unrecorded.
+    pub total_cancellations: u64,
+    /// Calls answered by a fallback since the breaker was created.
+    pub total_fallbacks: u64,
+    /// Failures recorded during the last `rolling_window`, whatever the
+    /// state.
+    pub rolling_failures: u32,
+    /// Successes recorded during the last `rolling_window`, whatever the
+    /// state.
+    pub rolling_successes: u32,
+    /// Slow calls recorded during the last `rolling_window`.
+    pub rolling_slow_calls: u32,
+    /// Calls currently held by the sliding window.
+    pub window_calls: u32,
+    /// Failures among `window_calls`.
+    pub window_failures: u32,
+    /// Slow calls among `window_calls`.
+    pub window_slow_calls: u32,
+}
+
+impl CircuitBreakerMetrics {
+    /// Failure percentage over the sliding window, if it holds any calls.
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.window_calls > 0)
+            .then(|| f64::from(self.window_failures) * 100.0 / f64::from(self.window_calls))
+    }
+
+    /// Slow-call percentage over the sliding window, if it holds any calls.
+    pub fn slow_call_rate(&self) -> Option<f64> {
+        (self.window_calls > 0)
+            .then(|| f64::from(self.window_slow_calls) * 100.0 / f64::from(self.window_calls))
+    }
+}
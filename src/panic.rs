@@ -0,0 +1,93 @@
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// What `execute` does when the protected call panics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PanicPolicy {
+    /// Don't catch the panic. It unwinds through `execute` and the call is
+    /// counted as cancelled.
+    #[default]
+    Propagate,
+    /// Record the call as a failure, then resume unwinding.
+    Resume,
+    /// Record the call as a failure and return
+    /// `CircuitBreakerError::Panicked`.
+    Return,
+}
+
+/// A panic caught in a protected call.
+pub struct PanicPayload(Box<dyn Any + Send>);
+
+impl PanicPayload {
+    /// The panic message, if the panic was raised with one.
+    pub fn message(&self) -> Option<&str> {
+        match self.0.downcast_ref::<&'static str>() {
+            Some(message) => Some(message),
+            None => self.0.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+
+    pub fn into_inner(self) -> Box<dyn Any + Send> {
+        self.0
+    }
+
+    /// Continues unwinding with the original payload.
+    pub fn resume(self) -> ! {
+        panic::resume_unwind(self.0)
+    }
+}
+
+impl fmt::Debug for PanicPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => f.debug_tuple("PanicPayload").field(&message).finish(),
+            None => f.write_str("PanicPayload(..)"),
+        }
+    }
+}
+
+// Payloads can't be compared, so they compare by message.
+impl PartialEq for PanicPayload {
+    fn eq(&self, other: &Self) -> bool {
+        self.message() == other.message()
+    }
+}
+
+// Runs `func` and awaits the future it returns, catching a panic in either.
+pub(crate) async fn catch_unwind<F, Fut>(func: F) -> Result<Fut::Output, PanicPayload>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future,
+{
+    let future = panic::catch_unwind(AssertUnwindSafe(func)).map_err(PanicPayload)?;
+    CatchUnwind {
+        future: Box::pin(future),
+    }
+    .await
+}
+
+struct CatchUnwind<F> {
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, PanicPayload>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
+        match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(PanicPayload(payload))),
+        }
+    }
+}
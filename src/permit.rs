@@ -0,0 +1,97 @@
+use crate::{Admission, CallOutcome, CircuitBreaker, CircuitBreakerConfig, Ending};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Admission of a single call, returned by `CircuitBreaker::try_acquire` for
+/// call sites that can't hand `execute` a closure.
+///
+/// Report how the call went with one of the `record_*` methods. A permit
+/// dropped without a recorded outcome counts as the configured
+/// `dropped_permit_outcome`, so a half-open probe slot is never leaked.
+#[must_use = "a permit counts as `dropped_permit_outcome` if dropped unrecorded"]
+pub struct Permit {
+    breaker: CircuitBreaker,
+    admission: Option<Admission>,
+    started: Instant,
+    // Held by `execute`: dropping it means the caller dropped the call.
+    cancel_on_drop: bool,
+}
+
+impl Permit {
+    pub(crate) fn new(breaker: CircuitBreaker, admission: Admission) -> Self {
+        let started = breaker.now();
+        Self {
+            breaker,
+            admission: Some(admission),
+            started,
+            cancel_on_drop: false,
+        }
+    }
+
+    pub(crate) fn cancel_on_drop(mut self) -> Self {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    /// Time since the permit was acquired, by the breaker's clock.
+    pub fn elapsed(&self) -> Duration {
+        self.breaker.now().saturating_duration_since(self.started)
+    }
+
+    /// Whether the call is one of the breaker's half-open probes.
+    pub fn is_probe(&self) -> bool {
+        self.admission
+            .as_ref()
+            .is_some_and(|admission| admission.probe)
+    }
+
+    // The configuration the call was admitted under.
+    pub(crate) fn config(&self) -> Option<Arc<CircuitBreakerConfig>> {
+        self.admission
+            .as_ref()
+            .map(|admission| Arc::clone(&admission.config))
+    }
+
+    pub fn record_success(mut self, elapsed: Duration) {
+        self.finish(CallOutcome::Success, elapsed, Ending::Returned);
+    }
+
+    /// Records a failed call and hands `err` back, so it can be returned
+    /// right away.
+    pub fn record_failure<E>(mut self, err: E, elapsed: Duration) -> E {
+        self.finish(CallOutcome::Failure, elapsed, Ending::Returned);
+        err
+    }
+
+    pub fn record(mut self, outcome: CallOutcome, elapsed: Duration) {
+        self.finish(outcome, elapsed, Ending::Returned);
+    }
+
+    pub(crate) fn record_timeout(mut self, elapsed: Duration) {
+        let outcome = match &self.admission {
+            Some(admission) if admission.config.timeout_counts_as_failure => CallOutcome::Failure,
+            _ => CallOutcome::Ignored,
+        };
+        self.finish(outcome, elapsed, Ending::TimedOut);
+    }
+
+    fn finish(&mut self, outcome: CallOutcome, elapsed: Duration, ending: Ending) {
+        if let Some(admission) = self.admission.take() {
+            self.breaker.finish(&admission, outcome, elapsed, ending);
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(admission) = &self.admission {
+            let outcome = if self.cancel_on_drop {
+                admission.config.cancellation_outcome
+            } else {
+                admission.config.dropped_permit_outcome
+            };
+            let elapsed = self.elapsed();
+            self.finish(outcome, elapsed, Ending::Cancelled);
+        }
+    }
+}
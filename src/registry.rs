@@ -0,0 +1,281 @@
+use crate::{CircuitBreaker, CircuitBreakerConfig, CircuitBreakerState, Clock, SystemClock};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Named breakers, created on first use from a shared default configuration
+/// or a per-name override.
+///
+/// The registry is a cheaply cloneable handle; every clone sees the same
+/// breakers.
+#[derive(Clone)]
+pub struct CircuitBreakerRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
+    clock: Arc<dyn Clock>,
+    entries: Mutex<Entries>,
+}
+
+struct Entries {
+    default: CircuitBreakerConfig,
+    overrides: HashMap<String, CircuitBreakerConfig>,
+    breakers: BTreeMap<String, CircuitBreaker>,
+}
+
+impl Entries {
+    fn config_for(&self, name: &str) -> &CircuitBreakerConfig {
+        self.overrides.get(name).unwrap_or(&self.default)
+    }
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new(default: CircuitBreakerConfig) -> Self {
+        Self::with_clock(default, SystemClock)
+    }
+
+    /// Every breaker the registry creates shares `clock`.
+    pub fn with_clock<K: Clock>(default: CircuitBreakerConfig, clock: K) -> Self {
+        Self {
+            inner: Arc::new(RegistryInner {
+                clock: Arc::new(clock),
+                entries: Mutex::new(Entries {
+                    default,
+                    overrides: HashMap::new(),
+                    breakers: BTreeMap::new(),
+                }),
+            }),
+        }
+    }
+
+    /// A registry using the file's `[default]` section for unnamed breakers
+    /// and `config_for(name)` for every breaker the file names.
+    #[cfg(feature = "serde")]
+    pub fn from_file(file: &crate::CircuitBreakerFile) -> Result<Self, crate::LoadError> {
+        let registry = Self::new(file.default.build()?);
+        for (name, config) in file.configs()? {
+            registry.set_config(name, config);
+        }
+        Ok(registry)
+    }
+
+    pub fn default_config(&self) -> CircuitBreakerConfig {
+        self.entries().default.clone()
+    }
+
+    /// Replaces the configuration of breakers without an override, including
+    /// the ones already created.
+    pub fn set_default_config(&self, config: CircuitBreakerConfig) {
+        let mut entries = self.entries();
+        for (name, breaker) in &entries.breakers {
+            if !entries.overrides.contains_key(name) {
+                breaker.update_config(config.clone());
+            }
+        }
+        entries.default = config;
+    }
+
+    /// Sets the configuration used for `name` instead of the default. An
+    /// existing breaker with that name is updated in place.
+    pub fn set_config(&self, name: impl Into<String>, config: CircuitBreakerConfig) {
+        let name = name.into();
+        let mut entries = self.entries();
+        if let Some(breaker) = entries.breakers.get(&name) {
+            breaker.update_config(config.clone());
+        }
+        entries.overrides.insert(name, config);
+    }
+
+    pub fn get(&self, name: &str) -> Option<CircuitBreaker> {
+        self.entries().breakers.get(name).cloned()
+    }
+
+    pub fn get_or_create(&self, name: &str) -> CircuitBreaker {
+        let mut entries = self.entries();
+        if let Some(breaker) = entries.breakers.get(name) {
+            return breaker.clone();
+        }
+        let breaker = CircuitBreaker::with_shared_clock(
+            entries.config_for(name).clone(),
+            Arc::clone(&self.inner.clock),
+        );
+        entries.breakers.insert(name.to_string(), breaker.clone());
+        breaker
+    }
+
+    /// Forgets the breaker; handles already given out keep working on their
+    /// own, and the next `get_or_create` starts a fresh one.
+    pub fn remove(&self, name: &str) -> Option<CircuitBreaker> {
+        self.entries().breakers.remove(name)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries().breakers.keys().cloned().collect()
+    }
+
+    /// Every breaker with its current state, sorted by name.
+    pub fn states(&self) -> Vec<(String, CircuitBreakerState)> {
+        self.entries()
+            .breakers
+            .iter()
+            .map(|(name, breaker)| (name.clone(), breaker.state()))
+            .collect()
+    }
+
+    pub fn reset_all(&self) {
+        self.for_each_matching("", CircuitBreaker::reset);
+    }
+
+    pub fn trip_all(&self) {
+        self.for_each_matching("", CircuitBreaker::trip);
+    }
+
+    pub fn reset_matching(&self, prefix: &str) {
+        self.for_each_matching(prefix, CircuitBreaker::reset);
+    }
+
+    /// Opens every breaker whose name starts with `prefix`.
+    pub fn trip_matching(&self, prefix: &str) {
+        self.for_each_matching(prefix, CircuitBreaker::trip);
+    }
+
+    /// Puts every breaker whose name starts with `prefix` in the
+    /// `ForcedOpen` override until `release_matching` is called.
+    pub fn force_open_matching(&self, prefix: &str) {
+        self.for_each_matching(prefix, CircuitBreaker::force_open);
+    }
+
+    pub fn release_matching(&self, prefix: &str) {
+        self.for_each_matching(prefix, CircuitBreaker::release);
+    }
+
+    pub fn release_all(&self) {
+        self.for_each_matching("", CircuitBreaker::release);
+    }
+
+    fn for_each_matching(&self, prefix: &str, action: impl Fn(&CircuitBreaker)) {
+        let entries = self.entries();
+        entries
+            .breakers
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .for_each(|(_, breaker)| action(breaker));
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.inner
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+
+    fn config(max_failures: u32) -> CircuitBreakerConfig {
+        CircuitBreakerConfig::builder()
+            .max_failures(max_failures)
+            .build()
+            .unwrap()
+    }
+
+    fn registry(names: &[&str]) -> CircuitBreakerRegistry {
+        let registry = CircuitBreakerRegistry::with_clock(config(5), MockClock::new());
+        for name in names {
+            registry.get_or_create(name);
+        }
+        registry
+    }
+
+    fn open(registry: &CircuitBreakerRegistry) -> Vec<String> {
+        registry
+            .states()
+            .into_iter()
+            .filter(|(_, state)| *state != CircuitBreakerState::Closed)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn get_or_create_uses_per_name_overrides() {
+        let registry = registry(&[]);
+        registry.set_config("db", config(2));
+
+        let db = registry.get_or_create("db");
+        assert_eq!(db.max_failures(), Some(2));
+        assert_eq!(registry.get_or_create("cache").max_failures(), Some(5));
+
+        // Later calls return the same breaker.
+        db.trip();
+        assert_eq!(
+            registry.get_or_create("db").state(),
+            CircuitBreakerState::Open
+        );
+        assert_eq!(registry.names(), ["cache", "db"]);
+    }
+
+    #[test]
+    fn set_default_config_skips_overridden_breakers() {
+        let registry = registry(&["cache"]);
+        registry.set_config("db", config(2));
+        let db = registry.get_or_create("db");
+
+        registry.set_default_config(config(9));
+        assert_eq!(registry.get("cache").unwrap().max_failures(), Some(9));
+        assert_eq!(db.max_failures(), Some(2));
+        assert_eq!(registry.get_or_create("queue").max_failures(), Some(9));
+    }
+
+    #[test]
+    fn prefix_operations_match_names_starting_with_prefix() {
+        let registry = registry(&["db", "db.a", "db.b", "dbx", "cache"]);
+        registry.trip_matching("db.");
+        assert_eq!(open(&registry), ["db.a", "db.b"]);
+        registry.reset_matching("db.");
+        assert!(open(&registry).is_empty());
+
+        registry.force_open_matching("db.");
+        assert_eq!(
+            registry.get("db.a").unwrap().state(),
+            CircuitBreakerState::ForcedOpen
+        );
+        assert_eq!(open(&registry), ["db.a", "db.b"]);
+        registry.release_matching("db.");
+        assert!(open(&registry).is_empty());
+
+        registry.trip_matching("db");
+        assert_eq!(open(&registry), ["db", "db.a", "db.b", "dbx"]);
+    }
+
+    #[test]
+    fn trip_all_and_reset_all_leave_overrides_alone() {
+        let registry = registry(&["db", "cache", "queue"]);
+        registry.get("db").unwrap().force_open();
+        registry.get("cache").unwrap().force_closed();
+
+        registry.trip_all();
+        assert_eq!(
+            registry.states(),
+            [
+                ("cache".to_string(), CircuitBreakerState::ForcedClosed),
+                ("db".to_string(), CircuitBreakerState::ForcedOpen),
+                ("queue".to_string(), CircuitBreakerState::Open),
+            ]
+        );
+        registry.reset_all();
+        assert_eq!(
+            registry.get("db").unwrap().state(),
+            CircuitBreakerState::ForcedOpen
+        );
+        assert_eq!(
+            registry.get("queue").unwrap().state(),
+            CircuitBreakerState::Closed
+        );
+
+        registry.release_all();
+        assert!(open(&registry).is_empty());
+    }
+}
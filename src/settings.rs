@@ -0,0 +1,713 @@
+use crate::{
+    Backoff, CallOutcome, CircuitBreakerConfig, CircuitBreakerConfigBuilder, ConfigError, Jitter,
+    LagPolicy, PanicPolicy, SlidingWindow,
+};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const ENV_PREFIX: &str = "RSSAFECIRCUIT";
+
+/// File formats a configuration can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    /// Picks the format from a `.toml`, `.yaml`/`.yml` or `.json` extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "toml" => Some(Format::Toml),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    fn parse<T: for<'de> Deserialize<'de>>(self, input: &str) -> Result<T, LoadError> {
+        match self {
+            Format::Toml => toml::from_str(input).map_err(|err| LoadError::Parse(err.to_string())),
+            Format::Yaml => {
+                serde_yaml::from_str(input).map_err(|err| LoadError::Parse(err.to_string()))
+            }
+            Format::Json => {
+                serde_json::from_str(input).map_err(|err| LoadError::Parse(err.to_string()))
+            }
+        }
+    }
+}
+
+/// Either a value or the string `"off"`, for settings that can be disabled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Toggle<T> {
+    On(T),
+    Off,
+}
+
+impl<T> Toggle<T> {
+    fn into_option(self) -> Option<T> {
+        match self {
+            Toggle::On(value) => Some(value),
+            Toggle::Off => None,
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Toggle<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw<T> {
+            Off(OffMarker),
+            On(T),
+        }
+
+        #[derive(Deserialize)]
+        enum OffMarker {
+            #[serde(rename = "off")]
+            Off,
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Off(OffMarker::Off) => Toggle::Off,
+            Raw::On(value) => Toggle::On(value),
+        })
+    }
+}
+
+/// A duration written the humantime way, such as `"30s"` or `"1m 30s"`.
+/// A bare number is read the same way, so `0` works but `30` asks for a
+/// unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Environment variables and YAML turn `0` into a number.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Text(String),
+            Number(u64),
+        }
+
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Text(text) => text,
+            Raw::Number(number) => number.to_string(),
+        };
+        humantime::parse_duration(&text)
+            .map(HumanDuration)
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum WindowSettings {
+    Count { size: u32 },
+    Time { window: HumanDuration, buckets: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackoffSettings {
+    pub multiplier: Option<f64>,
+    pub max_timeout: Option<HumanDuration>,
+    pub reset_after: Option<HumanDuration>,
+}
+
+/// A partial breaker configuration as written in a file or the environment.
+///
+/// Every setting is optional; unset ones fall through to the layer below
+/// (per-breaker section, then `[default]`, then `CircuitBreakerConfig`'s own
+/// defaults). Rules that can be disabled accept `"off"`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerSettings {
+    pub max_failures: Option<Toggle<u32>>,
+    pub failure_rate_threshold: Option<Toggle<f64>>,
+    pub sliding_window: Option<WindowSettings>,
+    pub slow_call_duration_threshold: Option<Toggle<HumanDuration>>,
+    pub slow_call_rate_threshold: Option<Toggle<f64>>,
+    pub minimum_calls: Option<u32>,
+    pub rolling_window: Option<HumanDuration>,
+    pub timeout: Option<HumanDuration>,
+    pub backoff: Option<Toggle<BackoffSettings>>,
+    pub jitter: Option<Jitter>,
+    pub rng_seed: Option<u64>,
+    pub call_timeout: Option<Toggle<HumanDuration>>,
+    pub timeout_counts_as_failure: Option<bool>,
+    pub dropped_permit_outcome: Option<CallOutcome>,
+    pub cancellation_outcome: Option<CallOutcome>,
+    pub panic_policy: Option<PanicPolicy>,
+    pub pause_time: Option<HumanDuration>,
+    pub half_open_max_calls: Option<u32>,
+    pub half_open_success_threshold: Option<u32>,
+    pub event_capacity: Option<usize>,
+    pub lag_policy: Option<LagPolicy>,
+    pub shadow: Option<bool>,
+}
+
+impl CircuitBreakerSettings {
+    pub fn parse(input: &str, format: Format) -> Result<Self, LoadError> {
+        format.parse(input)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let (input, format) = read(path.as_ref())?;
+        Self::parse(&input, format)
+    }
+
+    /// Settings for the breaker called `name` from `RSSAFECIRCUIT_<NAME>_<SETTING>`
+    /// environment variables, e.g. `RSSAFECIRCUIT_PAYMENTS_MAX_FAILURES=3`.
+    /// Nested settings use a double underscore:
+    /// `RSSAFECIRCUIT_PAYMENTS_BACKOFF__MULTIPLIER=2`.
+    pub fn from_env(name: &str) -> Result<Self, LoadError> {
+        Self::from_vars(name, std::env::vars())
+    }
+
+    /// Like `from_env`, reading from the given variables instead of the
+    /// process environment.
+    pub fn from_vars<I, K, V>(name: &str, vars: I) -> Result<Self, LoadError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let prefix = format!("{}_{}_", ENV_PREFIX, env_name(name));
+        let fields = setting_names();
+        let mut table = serde_json::Map::new();
+        for (key, value) in vars {
+            let Some(setting) = key.as_ref().strip_prefix(&prefix) else {
+                continue;
+            };
+            let setting = setting.to_ascii_lowercase();
+            let path: Vec<&str> = setting.split("__").collect();
+            if !fields.contains(&path[0]) {
+                // Most likely a variable of another breaker whose name
+                // starts with this one's.
+                continue;
+            }
+            insert(&mut table, &path, env_value(value.as_ref()));
+        }
+        serde_json::from_value(serde_json::Value::Object(table)).map_err(|err| LoadError::Env {
+            prefix,
+            message: err.to_string(),
+        })
+    }
+
+    /// Layers `overrides` on top of `self`.
+    pub fn merge(self, overrides: CircuitBreakerSettings) -> CircuitBreakerSettings {
+        CircuitBreakerSettings {
+            max_failures: overrides.max_failures.or(self.max_failures),
+            failure_rate_threshold: overrides
+                .failure_rate_threshold
+                .or(self.failure_rate_threshold),
+            sliding_window: overrides.sliding_window.or(self.sliding_window),
+            slow_call_duration_threshold: overrides
+                .slow_call_duration_threshold
+                .or(self.slow_call_duration_threshold),
+            slow_call_rate_threshold: overrides
+                .slow_call_rate_threshold
+                .or(self.slow_call_rate_threshold),
+            minimum_calls: overrides.minimum_calls.or(self.minimum_calls),
+            rolling_window: overrides.rolling_window.or(self.rolling_window),
+            timeout: overrides.timeout.or(self.timeout),
+            backoff: match (self.backoff, overrides.backoff) {
+                (Some(Toggle::On(base)), Some(Toggle::On(over))) => {
+                    Some(Toggle::On(BackoffSettings {
+                        multiplier: over.multiplier.or(base.multiplier),
+                        max_timeout: over.max_timeout.or(base.max_timeout),
+                        reset_after: over.reset_after.or(base.reset_after),
+                    }))
+                }
+                (base, over) => over.or(base),
+            },
+            jitter: overrides.jitter.or(self.jitter),
+            rng_seed: overrides.rng_seed.or(self.rng_seed),
+            call_timeout: overrides.call_timeout.or(self.call_timeout),
+            timeout_counts_as_failure: overrides
+                .timeout_counts_as_failure
+                .or(self.timeout_counts_as_failure),
+            dropped_permit_outcome: overrides
+                .dropped_permit_outcome
+                .or(self.dropped_permit_outcome),
+            cancellation_outcome: overrides.cancellation_outcome.or(self.cancellation_outcome),
+            panic_policy: overrides.panic_policy.or(self.panic_policy),
+            pause_time: overrides.pause_time.or(self.pause_time),
+            half_open_max_calls: overrides.half_open_max_calls.or(self.half_open_max_calls),
+            half_open_success_threshold: overrides
+                .half_open_success_threshold
+                .or(self.half_open_success_threshold),
+            event_capacity: overrides.event_capacity.or(self.event_capacity),
+            lag_policy: overrides.lag_policy.or(self.lag_policy),
+            shadow: overrides.shadow.or(self.shadow),
+        }
+    }
+
+    /// Applies the settings that are present to `builder`.
+    pub fn apply(&self, mut builder: CircuitBreakerConfigBuilder) -> CircuitBreakerConfigBuilder {
+        if let Some(value) = self.max_failures {
+            builder = builder.max_failures(value.into_option());
+        }
+        if let Some(value) = self.failure_rate_threshold {
+            builder = builder.failure_rate_threshold(value.into_option());
+        }
+        if let Some(value) = self.sliding_window {
+            builder = builder.sliding_window(match value {
+                WindowSettings::Count { size } => SlidingWindow::Count(size),
+                WindowSettings::Time { window, buckets } => SlidingWindow::Time {
+                    window: window.0,
+                    buckets,
+                },
+            });
+        }
+        if let Some(value) = self.slow_call_duration_threshold {
+            builder = builder.slow_call_duration_threshold(value.into_option().map(|d| d.0));
+        }
+        if let Some(value) = self.slow_call_rate_threshold {
+            builder = builder.slow_call_rate_threshold(value.into_option());
+        }
+        if let Some(value) = self.minimum_calls {
+            builder = builder.minimum_calls(value);
+        }
+        if let Some(value) = self.rolling_window {
+            builder = builder.rolling_window(value.0);
+        }
+        if let Some(value) = self.timeout {
+            builder = builder.timeout(value.0);
+        }
+        if let Some(value) = self.backoff {
+            builder = builder.backoff(value.into_option().map(|settings| {
+                let defaults = Backoff::default();
+                Backoff {
+                    multiplier: settings.multiplier.unwrap_or(defaults.multiplier),
+                    max_timeout: settings.max_timeout.map_or(defaults.max_timeout, |d| d.0),
+                    reset_after: settings.reset_after.map_or(defaults.reset_after, |d| d.0),
+                }
+            }));
+        }
+        if let Some(value) = self.jitter {
+            builder = builder.jitter(value);
+        }
+        if let Some(value) = self.rng_seed {
+            builder = builder.rng_seed(value);
+        }
+        if let Some(value) = self.call_timeout {
+            builder = builder.call_timeout(value.into_option().map(|d| d.0));
+        }
+        if let Some(value) = self.timeout_counts_as_failure {
+            builder = builder.timeout_counts_as_failure(value);
+        }
+        if let Some(value) = self.dropped_permit_outcome {
+            builder = builder.dropped_permit_outcome(value);
+        }
+        if let Some(value) = self.cancellation_outcome {
+            builder = builder.cancellation_outcome(value);
+        }
+        if let Some(value) = self.panic_policy {
+            builder = builder.panic_policy(value);
+        }
+        if let Some(value) = self.pause_time {
+            builder = builder.pause_time(value.0);
+        }
+        if let Some(value) = self.half_open_max_calls {
+            builder = builder.half_open_max_calls(value);
+        }
+        if let Some(value) = self.half_open_success_threshold {
+            builder = builder.half_open_success_threshold(value);
+        }
+        if let Some(value) = self.event_capacity {
+            builder = builder.event_capacity(value);
+        }
+        if let Some(value) = self.lag_policy {
+            builder = builder.lag_policy(value);
+        }
+        if let Some(value) = self.shadow {
+            builder = builder.shadow(value);
+        }
+        builder
+    }
+
+    pub fn build(&self) -> Result<CircuitBreakerConfig, LoadError> {
+        self.apply(CircuitBreakerConfig::builder())
+            .build()
+            .map_err(LoadError::Invalid)
+    }
+}
+
+/// A file describing many named breakers:
+///
+/// ```toml
+/// [default]
+/// timeout = "30s"
+///
+/// [breakers.payments]
+/// max_failures = 3
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerFile {
+    pub default: CircuitBreakerSettings,
+    pub breakers: BTreeMap<String, CircuitBreakerSettings>,
+}
+
+impl CircuitBreakerFile {
+    pub fn parse(input: &str, format: Format) -> Result<Self, LoadError> {
+        format.parse(input)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let (input, format) = read(path.as_ref())?;
+        Self::parse(&input, format)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.breakers.keys().map(String::as_str)
+    }
+
+    /// `[default]`, overridden by the breaker's own section, overridden by
+    /// its environment variables. Works for names without a section too.
+    pub fn settings_for(&self, name: &str) -> Result<CircuitBreakerSettings, LoadError> {
+        let named = self.breakers.get(name).cloned().unwrap_or_default();
+        Ok(self
+            .default
+            .clone()
+            .merge(named)
+            .merge(CircuitBreakerSettings::from_env(name)?))
+    }
+
+    pub fn config_for(&self, name: &str) -> Result<CircuitBreakerConfig, LoadError> {
+        self.settings_for(name)?.build()
+    }
+
+    /// Validated configurations for every breaker the file names.
+    pub fn configs(&self) -> Result<BTreeMap<String, CircuitBreakerConfig>, LoadError> {
+        self.names()
+            .map(|name| Ok((name.to_string(), self.config_for(name)?)))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    UnknownFormat {
+        path: PathBuf,
+    },
+    Parse(String),
+    Env {
+        prefix: String,
+        message: String,
+    },
+    Invalid(ConfigError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            LoadError::UnknownFormat { path } => write!(
+                f,
+                "cannot tell the format of {}; expected a .toml, .yaml, .yml or .json file",
+                path.display()
+            ),
+            LoadError::Parse(message) => write!(f, "invalid configuration: {}", message),
+            LoadError::Env { prefix, message } => {
+                write!(f, "invalid {}* environment variable: {}", prefix, message)
+            }
+            LoadError::Invalid(err) => write!(f, "invalid configuration: {}", err),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Invalid(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<(String, Format), LoadError> {
+    let format = Format::from_path(path).ok_or_else(|| LoadError::UnknownFormat {
+        path: path.to_path_buf(),
+    })?;
+    let input = fs::read_to_string(path).map_err(|source| LoadError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok((input, format))
+}
+
+// `payments-eu` -> `PAYMENTS_EU`
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Environment values are untyped: numbers and booleans are taken as such,
+// anything else (durations, "off", enum names) as a string.
+fn env_value(value: &str) -> serde_json::Value {
+    let value = value.trim();
+    if let Ok(value) = value.parse::<u64>() {
+        return value.into();
+    }
+    if let Ok(value) = value.parse::<i64>() {
+        return value.into();
+    }
+    if let Some(value) = value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+    {
+        return serde_json::Value::Number(value);
+    }
+    if let Ok(value) = value.parse::<bool>() {
+        return value.into();
+    }
+    value.into()
+}
+
+// Top-level keys of `CircuitBreakerSettings`, taken from its `Deserialize`
+// impl, used to pick the environment variables that belong to a breaker.
+fn setting_names() -> &'static [&'static str] {
+    let mut names: &'static [&'static str] = &[];
+    let _ = CircuitBreakerSettings::deserialize(FieldNames(&mut names));
+    names
+}
+
+// A deserializer that only records the field names a struct asks for.
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("only struct field names are collected"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        self.deserialize_any(visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+fn insert(
+    table: &mut serde_json::Map<String, serde_json::Value>,
+    path: &[&str],
+    value: serde_json::Value,
+) {
+    match path {
+        [] => {}
+        [key] => {
+            table.insert(key.to_string(), value);
+        }
+        [key, rest @ ..] => {
+            let entry = table
+                .entry(key.to_string())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            if !entry.is_object() {
+                *entry = serde_json::Value::Object(serde_json::Map::new());
+            }
+            if let serde_json::Value::Object(nested) = entry {
+                insert(nested, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+        [default]
+        timeout = "30s"
+        max_failures = 5
+
+        [breakers.payments]
+        max_failures = 3
+        backoff = { multiplier = 2.0, max_timeout = "5m" }
+    "#;
+
+    fn env(name: &str, vars: &[(&str, &str)]) -> Result<CircuitBreakerSettings, LoadError> {
+        CircuitBreakerSettings::from_vars(name, vars.iter().copied())
+    }
+
+    #[test]
+    fn env_overrides_file_sections() {
+        let file = CircuitBreakerFile::parse(FILE, Format::Toml).unwrap();
+        let vars = env(
+            "payments",
+            &[
+                ("RSSAFECIRCUIT_PAYMENTS_TIMEOUT", "45s"),
+                ("RSSAFECIRCUIT_PAYMENTS_BACKOFF__MULTIPLIER", "3"),
+                ("RSSAFECIRCUIT_PAYMENTS_FAILURE_RATE_THRESHOLD", "off"),
+                ("RSSAFECIRCUIT_PAYMENTS_SHADOW", "true"),
+            ],
+        )
+        .unwrap();
+        let config = file
+            .default
+            .clone()
+            .merge(file.breakers["payments"].clone())
+            .merge(vars)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.max_failures(), Some(3));
+        assert_eq!(config.timeout(), Duration::from_secs(45));
+        assert_eq!(config.failure_rate_threshold(), None);
+        assert!(config.shadow());
+        let backoff = config.backoff().unwrap();
+        assert_eq!(backoff.multiplier, 3.0);
+        assert_eq!(backoff.max_timeout, Duration::from_secs(300));
+    }
+
+    #[test]
+    fn env_ignores_breakers_with_longer_names() {
+        let settings = env(
+            "payments",
+            &[
+                ("RSSAFECIRCUIT_PAYMENTS_MAX_FAILURES", "2"),
+                ("RSSAFECIRCUIT_PAYMENTS_V2_MAX_FAILURES", "7"),
+                ("RSSAFECIRCUIT_OTHER_MAX_FAILURES", "9"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(settings.max_failures, Some(Toggle::On(2)));
+
+        let settings = env(
+            "payments-v2",
+            &[("RSSAFECIRCUIT_PAYMENTS_V2_MAX_FAILURES", "7")],
+        )
+        .unwrap();
+        assert_eq!(settings.max_failures, Some(Toggle::On(7)));
+    }
+
+    #[test]
+    fn env_values_keep_their_type() {
+        let settings = env(
+            "x",
+            &[
+                ("RSSAFECIRCUIT_X_RNG_SEED", "18446744073709551615"),
+                ("RSSAFECIRCUIT_X_SLOW_CALL_RATE_THRESHOLD", "12.5"),
+                ("RSSAFECIRCUIT_X_SLIDING_WINDOW__TYPE", "count"),
+                ("RSSAFECIRCUIT_X_SLIDING_WINDOW__SIZE", "20"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(settings.rng_seed, Some(u64::MAX));
+        assert_eq!(settings.slow_call_rate_threshold, Some(Toggle::On(12.5)));
+        assert_eq!(
+            settings.sliding_window,
+            Some(WindowSettings::Count { size: 20 })
+        );
+    }
+
+    #[test]
+    fn env_rejects_unknown_nested_settings() {
+        let err = env("x", &[("RSSAFECIRCUIT_X_BACKOFF__FACTOR", "2")]).unwrap_err();
+        assert!(matches!(err, LoadError::Env { .. }));
+    }
+
+    #[test]
+    fn durations_accept_bare_numbers_humantime_can_read() {
+        let settings = env(
+            "x",
+            &[
+                ("RSSAFECIRCUIT_X_PAUSE_TIME", "0"),
+                ("RSSAFECIRCUIT_X_CALL_TIMEOUT", "250ms"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(settings.pause_time, Some(HumanDuration(Duration::ZERO)));
+        assert_eq!(
+            settings.call_timeout,
+            Some(Toggle::On(HumanDuration(Duration::from_millis(250))))
+        );
+
+        let err = env("x", &[("RSSAFECIRCUIT_X_TIMEOUT", "30")]).unwrap_err();
+        assert!(err.to_string().contains("time unit needed"), "{}", err);
+    }
+
+    #[test]
+    fn yaml_and_json_files_match_toml() {
+        let yaml = r#"
+            default:
+              timeout: 30s
+              max_failures: 5
+              pause_time: 0
+            breakers:
+              payments:
+                max_failures: 3
+                backoff:
+                  multiplier: 2.0
+                  max_timeout: 5m
+        "#;
+        let json = r#"{
+            "default": { "timeout": "30s", "max_failures": 5, "pause_time": "0s" },
+            "breakers": {
+                "payments": {
+                    "max_failures": 3,
+                    "backoff": { "multiplier": 2.0, "max_timeout": "5m" }
+                }
+            }
+        }"#;
+        let toml = CircuitBreakerFile::parse(FILE, Format::Toml).unwrap();
+        for (input, format) in [(yaml, Format::Yaml), (json, Format::Json)] {
+            let file = CircuitBreakerFile::parse(input, format).unwrap();
+            assert_eq!(file.breakers, toml.breakers);
+            assert_eq!(file.default.timeout, toml.default.timeout);
+            assert_eq!(file.default.max_failures, toml.default.max_failures);
+            assert_eq!(file.default.pause_time, Some(HumanDuration(Duration::ZERO)));
+        }
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected_in_every_format() {
+        for (input, format) in [
+            ("max_failure = 3", Format::Toml),
+            ("max_failure: 3", Format::Yaml),
+            (r#"{ "max_failure": 3 }"#, Format::Json),
+        ] {
+            let err = CircuitBreakerSettings::parse(input, format).unwrap_err();
+            assert!(matches!(err, LoadError::Parse(_)), "{:?}", format);
+        }
+    }
+}
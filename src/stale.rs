@@ -0,0 +1,254 @@
+use crate::lru::Lru;
+use crate::{CircuitBreaker, CircuitBreakerError, ConfigError};
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// A value returned through a [`StaleCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cached<T> {
+    /// Returned by the call just made.
+    Fresh(T),
+    /// Served from the cache because the call was rejected or failed. `age`
+    /// is the time since the value was returned by a successful call.
+    Stale { value: T, age: Duration },
+}
+
+impl<T> Cached<T> {
+    pub fn is_fresh(&self) -> bool {
+        matches!(self, Cached::Fresh(_))
+    }
+
+    pub fn is_stale(&self) -> bool {
+        matches!(self, Cached::Stale { .. })
+    }
+
+    pub fn value(&self) -> &T {
+        match self {
+            Cached::Fresh(value) | Cached::Stale { value, .. } => value,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            Cached::Fresh(value) | Cached::Stale { value, .. } => value,
+        }
+    }
+}
+
+/// Stale-on-error layer over a breaker: remembers the last successful
+/// response per key and serves it when the breaker is open or the call
+/// fails, as long as it is younger than `ttl`.
+///
+/// At most `max_entries` responses are kept; the oldest one makes room for
+/// a new key.
+pub struct StaleCache<K, T> {
+    inner: Arc<CacheInner<K, T>>,
+}
+
+impl<K, T> Clone for StaleCache<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+struct CacheInner<K, T> {
+    breaker: CircuitBreaker,
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Lru<K, T>>,
+}
+
+impl<K: Hash + Eq + Clone, T: Clone> StaleCache<K, T> {
+    /// Returns an error if `ttl` or `max_entries` is zero.
+    pub fn new(
+        breaker: CircuitBreaker,
+        ttl: Duration,
+        max_entries: usize,
+    ) -> Result<Self, ConfigError> {
+        if ttl.is_zero() {
+            return Err(ConfigError::Zero { field: "ttl" });
+        }
+        if max_entries == 0 {
+            return Err(ConfigError::Zero {
+                field: "max_entries",
+            });
+        }
+        Ok(Self {
+            inner: Arc::new(CacheInner {
+                breaker,
+                ttl,
+                max_entries,
+                entries: Mutex::new(Lru::new()),
+            }),
+        })
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.inner.breaker
+    }
+
+    /// Runs `func` through the breaker. A successful response is remembered
+    /// for `key` and returned fresh; if the call is rejected or fails, the
+    /// last response for `key` is returned stale instead, or the error if
+    /// there is none younger than `ttl`. Stale responses count as fallbacks
+    /// in the breaker's metrics.
+    pub async fn execute<Q, F, Fut, E>(
+        &self,
+        key: &Q,
+        func: F,
+    ) -> Result<Cached<T>, CircuitBreakerError<E>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let breaker = &self.inner.breaker;
+        match breaker.execute(func).await {
+            Ok(value) => {
+                self.store(key, value.clone());
+                Ok(Cached::Fresh(value))
+            }
+            Err(err) => match self.get(key) {
+                Some((value, age)) => {
+                    breaker.count_fallback();
+                    Ok(Cached::Stale { value, age })
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// The remembered response for `key` and its age, if younger than `ttl`.
+    pub fn get<Q>(&self, key: &Q) -> Option<(T, Duration)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = self.inner.breaker.now();
+        let entries = self.entries();
+        let (value, stored_at) = entries.get(key)?;
+        let age = now.saturating_duration_since(stored_at);
+        (age < self.inner.ttl).then(|| (value.clone(), age))
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.entries().remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    fn store<Q>(&self, key: &Q, value: T)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let now = self.inner.breaker.now();
+        let mut entries = self.entries();
+        entries.remove_unused(now, self.inner.ttl);
+        match entries.touch(key, now) {
+            Some(stored) => *stored = value,
+            None => entries.insert(key.to_owned(), value, now, Some(self.inner.max_entries)),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Lru<K, T>> {
+        self.inner
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CircuitBreakerConfig, MockClock};
+
+    fn cache(max_entries: usize) -> (StaleCache<String, u32>, MockClock) {
+        let clock = MockClock::new();
+        let breaker = CircuitBreaker::with_clock(CircuitBreakerConfig::default(), clock.clone());
+        (
+            StaleCache::new(breaker, Duration::from_secs(30), max_entries).unwrap(),
+            clock,
+        )
+    }
+
+    async fn answer(cache: &StaleCache<String, u32>, key: &str, value: u32) -> Cached<u32> {
+        cache
+            .execute(key, || async move { Ok::<_, ()>(value) })
+            .await
+            .unwrap()
+    }
+
+    async fn fail(
+        cache: &StaleCache<String, u32>,
+        key: &str,
+    ) -> Result<Cached<u32>, CircuitBreakerError<()>> {
+        cache.execute(key, || async { Err(()) }).await
+    }
+
+    #[tokio::test]
+    async fn serves_stale_value_until_ttl() {
+        let (cache, clock) = cache(10);
+        assert_eq!(answer(&cache, "a", 1).await, Cached::Fresh(1));
+
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(
+            fail(&cache, "a").await,
+            Ok(Cached::Stale {
+                value: 1,
+                age: Duration::from_secs(20),
+            })
+        );
+        assert_eq!(cache.breaker().metrics().total_fallbacks, 1);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(fail(&cache, "a").await, Err(CircuitBreakerError::Inner(())));
+        assert!(fail(&cache, "b").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn keeps_at_most_max_entries() {
+        let (cache, _) = cache(2);
+        answer(&cache, "a", 1).await;
+        answer(&cache, "b", 2).await;
+        answer(&cache, "a", 3).await;
+        answer(&cache, "c", 4).await;
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("a").map(|(value, _)| value), Some(3));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c").map(|(value, _)| value), Some(4));
+    }
+
+    #[test]
+    fn rejects_zero_ttl_and_max_entries() {
+        let breaker = CircuitBreaker::with_config(CircuitBreakerConfig::default());
+        assert!(matches!(
+            StaleCache::<String, u32>::new(breaker.clone(), Duration::ZERO, 10),
+            Err(ConfigError::Zero { field: "ttl" })
+        ));
+        assert!(matches!(
+            StaleCache::<String, u32>::new(breaker, Duration::from_secs(30), 0),
+            Err(ConfigError::Zero {
+                field: "max_entries"
+            })
+        ));
+    }
+}
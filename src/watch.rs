@@ -0,0 +1,205 @@
+use crate::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerFile, CircuitBreakerSettings, ConfigError,
+    LoadError,
+};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// The task started by `CircuitBreaker::watch_config`.
+///
+/// Reload failures leave the breaker as it is and are reported here instead:
+/// `last_error` holds the error of the most recent reload, and `changed`
+/// waits for it to change.
+pub struct ConfigWatcher {
+    task: JoinHandle<()>,
+    status: watch::Receiver<Option<Arc<LoadError>>>,
+}
+
+impl ConfigWatcher {
+    /// The error of the most recent reload, or `None` if it succeeded.
+    pub fn last_error(&self) -> Option<Arc<LoadError>> {
+        self.status.borrow().clone()
+    }
+
+    /// Waits until a reload fails with a new error or succeeds after a
+    /// failure. Returns `false` instead once the watcher has stopped.
+    pub async fn changed(&mut self) -> bool {
+        self.status.changed().await.is_ok()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stops watching.
+    pub fn abort(&self) {
+        self.task.abort();
+    }
+
+    /// Waits for the watcher to stop, which happens once every handle to
+    /// the breaker is dropped or the watcher is aborted.
+    pub async fn stopped(self) {
+        let _ = self.task.await;
+    }
+}
+
+impl CircuitBreaker {
+    /// Re-reads `path` every `interval` and applies the configuration it
+    /// describes with `update_config` whenever it differs from the current
+    /// one. With a `name` the file is read as a `CircuitBreakerFile` and that
+    /// breaker's settings are resolved like `config_for(name)`, including its
+    /// `RSSAFECIRCUIT_<NAME>_*` environment variables. With `None` it holds a
+    /// single breaker's settings and no environment variables are applied,
+    /// as there is no name to look them up by. Files that fail to load leave
+    /// the breaker as it is and are reported through the returned
+    /// [`ConfigWatcher`]. The task ends once every handle to the breaker is
+    /// dropped. Returns an error for a zero `interval`.
+    pub fn watch_config(
+        &self,
+        path: impl Into<PathBuf>,
+        name: Option<&str>,
+        interval: Duration,
+    ) -> Result<ConfigWatcher, ConfigError> {
+        if interval.is_zero() {
+            return Err(ConfigError::Zero { field: "interval" });
+        }
+        let path = path.into();
+        let name = name.map(str::to_string);
+        let breaker = Arc::downgrade(&self.inner);
+        let clock = Arc::clone(&self.inner.clock);
+        let (status, receiver) = watch::channel(None::<Arc<LoadError>>);
+        let task = tokio::spawn(async move {
+            while breaker.strong_count() > 0 {
+                let (path, name) = (path.clone(), name.clone());
+                let loaded =
+                    tokio::task::spawn_blocking(move || load(&path, name.as_deref())).await;
+                let Some(inner) = breaker.upgrade() else {
+                    return;
+                };
+                let breaker = CircuitBreaker { inner };
+                match loaded {
+                    Ok(Ok(config)) => {
+                        status.send_if_modified(|status| status.take().is_some());
+                        if *breaker.config() != config {
+                            breaker.update_config(config);
+                        }
+                    }
+                    // Only report an error again once it changes.
+                    Ok(Err(err)) => {
+                        status.send_if_modified(|status| {
+                            let changed = status
+                                .as_ref()
+                                .is_none_or(|last| last.to_string() != err.to_string());
+                            if changed {
+                                *status = Some(Arc::new(err));
+                            }
+                            changed
+                        });
+                    }
+                    // Loading panicked; try again next time.
+                    Err(_) => {}
+                }
+                drop(breaker);
+                clock.sleep(interval).await;
+            }
+        });
+        Ok(ConfigWatcher {
+            task,
+            status: receiver,
+        })
+    }
+}
+
+fn load(path: &Path, name: Option<&str>) -> Result<CircuitBreakerConfig, LoadError> {
+    match name {
+        Some(name) => CircuitBreakerFile::from_path(path)?.config_for(name),
+        None => CircuitBreakerSettings::from_path(path)?.build(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CircuitBreakerState, MockClock, TransitionReason};
+    use std::time::Duration;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("rssafecircuit-{}-{}.toml", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+
+        fn write(&self, contents: &str) {
+            std::fs::write(&self.0, contents).unwrap();
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn applies_file_changes_and_reports_failures() {
+        let file = TempFile::new("watch");
+        file.write("[breakers.api]\nmax_failures = 2\n");
+        let clock = MockClock::new();
+        let breaker = CircuitBreaker::with_clock(CircuitBreakerConfig::default(), clock.clone());
+        let mut events = breaker.subscribe();
+        let mut watcher = breaker
+            .watch_config(&file.0, Some("api"), Duration::from_secs(1))
+            .unwrap();
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.reason, TransitionReason::ConfigChanged);
+        assert_eq!(breaker.max_failures(), Some(2));
+
+        file.write("[breakers.api]\nmax_failures = 0\n");
+        clock.advance(Duration::from_secs(1));
+        assert!(watcher.changed().await);
+        let err = watcher.last_error().unwrap();
+        assert!(matches!(*err, LoadError::Invalid(ConfigError::Zero { .. })));
+        assert_eq!(breaker.max_failures(), Some(2));
+
+        file.write("[breakers.api]\nmax_failures = 4\n");
+        clock.advance(Duration::from_secs(1));
+        assert!(watcher.changed().await);
+        assert!(watcher.last_error().is_none());
+        assert_eq!(breaker.max_failures(), Some(4));
+        assert_eq!(breaker.state(), CircuitBreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn stops_once_the_breaker_is_dropped() {
+        let file = TempFile::new("stop");
+        file.write("max_failures = 2\n");
+        let clock = MockClock::new();
+        let breaker = CircuitBreaker::with_clock(CircuitBreakerConfig::default(), clock.clone());
+        let mut events = breaker.subscribe();
+        let watcher = breaker
+            .watch_config(&file.0, None, Duration::from_secs(1))
+            .unwrap();
+        events.recv().await.unwrap();
+
+        drop(breaker);
+        clock.advance(Duration::from_secs(1));
+        tokio::time::timeout(Duration::from_secs(5), watcher.stopped())
+            .await
+            .expect("the watcher stops");
+    }
+
+    #[tokio::test]
+    async fn rejects_a_zero_interval() {
+        let breaker = CircuitBreaker::with_config(CircuitBreakerConfig::default());
+        assert!(matches!(
+            breaker.watch_config("unused.toml", None, Duration::ZERO),
+            Err(ConfigError::Zero { field: "interval" })
+        ));
+    }
+}
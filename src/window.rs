@@ -0,0 +1,341 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The population of calls the failure rate is computed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlidingWindow {
+    /// The last `n` calls.
+    Count(u32),
+    /// Calls made during the last `window`, aggregated into `buckets` equal
+    /// slices that expire one at a time.
+    Time { window: Duration, buckets: u32 },
+}
+
+impl Default for SlidingWindow {
+    fn default() -> Self {
+        SlidingWindow::Count(100)
+    }
+}
+
+// Outcome of a single call as the window sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Sample {
+    pub(crate) failed: bool,
+    pub(crate) slow: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct WindowStats {
+    pub(crate) calls: u32,
+    pub(crate) failures: u32,
+    pub(crate) slow_calls: u32,
+}
+
+impl WindowStats {
+    fn add(&mut self, sample: Sample) {
+        self.calls = self.calls.saturating_add(1);
+        if sample.failed {
+            self.failures = self.failures.saturating_add(1);
+        }
+        if sample.slow {
+            self.slow_calls = self.slow_calls.saturating_add(1);
+        }
+    }
+
+    fn remove(&mut self, sample: Sample) {
+        self.calls -= 1;
+        if sample.failed {
+            self.failures -= 1;
+        }
+        if sample.slow {
+            self.slow_calls -= 1;
+        }
+    }
+
+    fn merge(self, other: WindowStats) -> WindowStats {
+        WindowStats {
+            calls: self.calls.saturating_add(other.calls),
+            failures: self.failures.saturating_add(other.failures),
+            slow_calls: self.slow_calls.saturating_add(other.slow_calls),
+        }
+    }
+}
+
+pub(crate) enum Window {
+    Count(CountWindow),
+    Time(TimeWindow),
+}
+
+impl Window {
+    pub(crate) fn new(kind: SlidingWindow, now: Instant) -> Self {
+        match kind {
+            SlidingWindow::Count(size) => Window::Count(CountWindow::new(size)),
+            SlidingWindow::Time { window, buckets } => {
+                Window::Time(TimeWindow::new(window, buckets, now))
+            }
+        }
+    }
+
+    pub(crate) fn record(&mut self, now: Instant, sample: Sample) {
+        match self {
+            Window::Count(window) => window.record(sample),
+            Window::Time(window) => window.record(now, sample),
+        }
+    }
+
+    pub(crate) fn stats(&self, now: Instant) -> WindowStats {
+        match self {
+            Window::Count(window) => window.stats(),
+            Window::Time(window) => window.stats(now),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            Window::Count(window) => window.clear(),
+            Window::Time(window) => window.clear(),
+        }
+    }
+
+    // Switches to a new window kind or size. A count window keeps its most
+    // recent samples and a time window regroups its buckets; switching
+    // between kinds starts over empty.
+    pub(crate) fn resize(&mut self, kind: SlidingWindow, now: Instant) {
+        match (self, kind) {
+            (Window::Count(window), SlidingWindow::Count(size)) => window.resize(size),
+            (
+                Window::Time(window),
+                SlidingWindow::Time {
+                    window: span,
+                    buckets,
+                },
+            ) => window.resize(span, buckets, now),
+            (window, kind) => *window = Window::new(kind, now),
+        }
+    }
+}
+
+// Outcomes of the last `size` calls.
+pub(crate) struct CountWindow {
+    samples: VecDeque<Sample>,
+    size: usize,
+    stats: WindowStats,
+}
+
+impl CountWindow {
+    fn new(size: u32) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(size as usize),
+            size: size as usize,
+            stats: WindowStats::default(),
+        }
+    }
+
+    fn record(&mut self, sample: Sample) {
+        if self.size == 0 {
+            return;
+        }
+        if self.samples.len() == self.size {
+            if let Some(evicted) = self.samples.pop_front() {
+                self.stats.remove(evicted);
+            }
+        }
+        self.samples.push_back(sample);
+        self.stats.add(sample);
+    }
+
+    fn stats(&self) -> WindowStats {
+        self.stats
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.stats = WindowStats::default();
+    }
+
+    fn resize(&mut self, size: u32) {
+        self.size = size as usize;
+        while self.samples.len() > self.size {
+            if let Some(evicted) = self.samples.pop_front() {
+                self.stats.remove(evicted);
+            }
+        }
+    }
+}
+
+struct Bucket {
+    // Index of the bucket's time slice, counted from the window's origin.
+    epoch: u64,
+    stats: WindowStats,
+}
+
+// Outcomes of the calls made during the last `width * len` of time, one
+// bucket per slice.
+pub(crate) struct TimeWindow {
+    origin: Instant,
+    width: Duration,
+    len: u64,
+    buckets: VecDeque<Bucket>,
+}
+
+impl TimeWindow {
+    fn new(window: Duration, buckets: u32, now: Instant) -> Self {
+        let len = buckets.max(1);
+        Self {
+            origin: now,
+            width: (window / len).max(Duration::from_nanos(1)),
+            len: u64::from(len),
+            buckets: VecDeque::with_capacity(len as usize),
+        }
+    }
+
+    fn epoch(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.origin).as_nanos() / self.width.as_nanos()) as u64
+    }
+
+    fn is_live(&self, bucket: &Bucket, epoch: u64) -> bool {
+        bucket.epoch + self.len > epoch
+    }
+
+    fn record(&mut self, now: Instant, sample: Sample) {
+        let epoch = self.epoch(now);
+        while let Some(front) = self.buckets.front() {
+            if self.is_live(front, epoch) {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+        if self.buckets.back().map(|bucket| bucket.epoch) != Some(epoch) {
+            self.buckets.push_back(Bucket {
+                epoch,
+                stats: WindowStats::default(),
+            });
+        }
+        if let Some(bucket) = self.buckets.back_mut() {
+            bucket.stats.add(sample);
+        }
+    }
+
+    fn stats(&self, now: Instant) -> WindowStats {
+        let epoch = self.epoch(now);
+        self.buckets
+            .iter()
+            .filter(|bucket| self.is_live(bucket, epoch))
+            .fold(WindowStats::default(), |total, bucket| {
+                total.merge(bucket.stats)
+            })
+    }
+
+    fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    // Regroups the recorded buckets into slices of the new width. Samples
+    // are placed by the start of their old slice, so they may expire up to
+    // one old slice early but never late.
+    fn resize(&mut self, window: Duration, buckets: u32, now: Instant) {
+        let old = std::mem::replace(self, TimeWindow::new(window, buckets, self.origin));
+        for bucket in old.buckets {
+            let start = u128::from(bucket.epoch) * old.width.as_nanos();
+            let epoch = (start / self.width.as_nanos()) as u64;
+            match self.buckets.back_mut() {
+                Some(last) if last.epoch == epoch => last.stats = last.stats.merge(bucket.stats),
+                _ => self.buckets.push_back(Bucket {
+                    epoch,
+                    stats: bucket.stats,
+                }),
+            }
+        }
+        let (current, len) = (self.epoch(now), self.len);
+        self.buckets.retain(|bucket| bucket.epoch + len > current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAILURE: Sample = Sample {
+        failed: true,
+        slow: false,
+    };
+    const SLOW_SUCCESS: Sample = Sample {
+        failed: false,
+        slow: true,
+    };
+
+    #[test]
+    fn count_window_keeps_last_calls() {
+        let now = Instant::now();
+        let mut window = Window::new(SlidingWindow::Count(3), now);
+        window.record(now, FAILURE);
+        window.record(now, FAILURE);
+        window.record(now, SLOW_SUCCESS);
+        window.record(now, SLOW_SUCCESS);
+        assert_eq!(
+            window.stats(now),
+            WindowStats {
+                calls: 3,
+                failures: 1,
+                slow_calls: 2,
+            }
+        );
+
+        window.resize(SlidingWindow::Count(1), now);
+        assert_eq!(window.stats(now).calls, 1);
+        assert_eq!(window.stats(now).failures, 0);
+    }
+
+    #[test]
+    fn time_window_expires_one_bucket_at_a_time() {
+        let start = Instant::now();
+        let second = |secs| start + Duration::from_secs(secs);
+        let mut window = Window::new(
+            SlidingWindow::Time {
+                window: Duration::from_secs(10),
+                buckets: 5,
+            },
+            start,
+        );
+        window.record(second(0), FAILURE);
+        window.record(second(3), SLOW_SUCCESS);
+        assert_eq!(window.stats(second(9)).calls, 2);
+
+        // The first bucket covered [0s, 2s) and is out of the window at 10s.
+        let stats = window.stats(second(10));
+        assert_eq!((stats.calls, stats.failures, stats.slow_calls), (1, 0, 1));
+        assert_eq!(window.stats(second(14)).calls, 0);
+
+        window.record(second(20), FAILURE);
+        assert_eq!(window.stats(second(20)).failures, 1);
+    }
+
+    #[test]
+    fn resized_time_window_keeps_its_samples() {
+        let start = Instant::now();
+        let second = |secs| start + Duration::from_secs(secs);
+        let time = |secs, buckets| SlidingWindow::Time {
+            window: Duration::from_secs(secs),
+            buckets,
+        };
+        let filled = || {
+            let mut window = Window::new(time(10, 5), start);
+            for secs in [0, 3, 7] {
+                window.record(second(secs), FAILURE);
+            }
+            window
+        };
+
+        let mut grown = filled();
+        grown.resize(time(20, 4), second(8));
+        assert_eq!(grown.stats(second(8)).failures, 3);
+        // Only the sample from the [6s, 8s) slice is within 20s of 21s.
+        assert_eq!(grown.stats(second(21)).failures, 1);
+
+        let mut shrunk = filled();
+        shrunk.resize(time(4, 2), second(8));
+        assert_eq!(shrunk.stats(second(8)).failures, 1);
+        shrunk.record(second(8), FAILURE);
+        assert_eq!(shrunk.stats(second(9)).failures, 2);
+    }
+}